@@ -0,0 +1,6 @@
+ALTER TABLE users
+    ADD COLUMN role TEXT NOT NULL DEFAULT 'author'
+    CHECK (role IN ('admin', 'editor', 'author', 'contributor'));
+
+-- every existing account had full access before roles existed
+UPDATE users SET role = 'admin';
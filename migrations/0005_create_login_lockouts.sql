@@ -0,0 +1,10 @@
+CREATE TABLE login_lockouts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    email TEXT,
+    ip_address TEXT,
+    failures INTEGER NOT NULL,
+    locked_until TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX login_lockouts_created_at_idx ON login_lockouts (created_at DESC);
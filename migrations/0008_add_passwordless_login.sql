@@ -0,0 +1,2 @@
+ALTER TABLE users
+    ADD COLUMN passwordless_enabled BOOLEAN NOT NULL DEFAULT false;
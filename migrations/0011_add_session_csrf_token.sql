@@ -0,0 +1,4 @@
+-- sessions created before this migration get no token and have to log in
+-- again before making cookie-authenticated changes
+ALTER TABLE sessions
+    ADD COLUMN csrf_token_hash TEXT;
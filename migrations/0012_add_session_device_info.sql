@@ -0,0 +1,6 @@
+ALTER TABLE sessions
+    ADD COLUMN user_agent TEXT,
+    ADD COLUMN ip_address TEXT,
+    ADD COLUMN last_active_at TIMESTAMPTZ;
+
+UPDATE sessions SET last_active_at = created_at;
@@ -0,0 +1,6 @@
+-- sessions created before this migration lasted 30 days, like "remember me" ones
+ALTER TABLE sessions
+    ADD COLUMN remember_me BOOLEAN NOT NULL DEFAULT true;
+
+ALTER TABLE sessions
+    ALTER COLUMN remember_me DROP DEFAULT;
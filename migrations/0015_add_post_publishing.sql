@@ -0,0 +1,16 @@
+ALTER TABLE posts
+    ADD COLUMN published_at TIMESTAMPTZ;
+
+-- `draft` was never set by the API, so every existing post was already
+-- public. Keep those published and only leave explicit drafts unpublished.
+UPDATE posts
+SET draft = false,
+    published_at = COALESCE(updated_at, created_at)
+WHERE draft IS NOT TRUE;
+
+ALTER TABLE posts
+    ALTER COLUMN draft SET DEFAULT true,
+    ALTER COLUMN draft SET NOT NULL,
+    ADD CONSTRAINT posts_draft_published_at_check CHECK (draft = (published_at IS NULL));
+
+CREATE INDEX posts_published_at_idx ON posts (published_at DESC) WHERE published_at IS NOT NULL;
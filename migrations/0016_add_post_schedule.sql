@@ -0,0 +1,11 @@
+-- `publish_at` is the time a draft goes live, `unpublish_at` the time a post
+-- turns back into a draft. The scheduler clears each once it has applied it.
+ALTER TABLE posts
+    ADD COLUMN publish_at TIMESTAMPTZ,
+    ADD COLUMN unpublish_at TIMESTAMPTZ,
+    ADD CONSTRAINT posts_schedule_order_check CHECK (
+        publish_at IS NULL OR unpublish_at IS NULL OR unpublish_at > publish_at
+    );
+
+CREATE INDEX posts_publish_at_idx ON posts (publish_at) WHERE publish_at IS NOT NULL;
+CREATE INDEX posts_unpublish_at_idx ON posts (unpublish_at) WHERE unpublish_at IS NOT NULL;
@@ -0,0 +1,47 @@
+-- One snapshot of the editable fields per save, numbered per post.
+CREATE TABLE post_revisions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    post_id UUID NOT NULL REFERENCES posts (id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    author_id UUID REFERENCES users (id) ON DELETE SET NULL,
+    title TEXT NOT NULL,
+    slug TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    content TEXT NOT NULL,
+    category UUID NOT NULL,
+    read_time_millis BIGINT NOT NULL,
+    cover_image TEXT NOT NULL,
+    tags JSONB NOT NULL DEFAULT '[]',
+    -- set when the revision was created by restoring an older one
+    restored_from INTEGER,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (post_id, revision)
+);
+
+-- the current state of every existing post becomes its first revision
+INSERT INTO post_revisions (
+    post_id, revision, author_id, title, slug, summary, content, category,
+    read_time_millis, cover_image, tags, created_at
+)
+SELECT
+    posts.id,
+    1,
+    posts.author,
+    posts.title,
+    posts.slug,
+    posts.summary,
+    posts.content,
+    posts.category,
+    posts.read_time_millis,
+    posts.cover_image,
+    COALESCE(
+        (
+            SELECT jsonb_agg(tags.name ORDER BY tags.name)
+            FROM post_tags
+            JOIN tags ON tags.id = post_tags.tag_id
+            WHERE post_tags.post_id = posts.id
+        ),
+        '[]'
+    ),
+    COALESCE(posts.updated_at, posts.created_at)
+FROM posts;
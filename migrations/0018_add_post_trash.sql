@@ -0,0 +1,6 @@
+-- Deleted posts stay in the trash until purged, by hand or once they are
+-- older than the configured retention.
+ALTER TABLE posts
+    ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX posts_deleted_at_idx ON posts (deleted_at) WHERE deleted_at IS NOT NULL;
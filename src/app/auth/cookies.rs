@@ -0,0 +1,201 @@
+use axum_extra::extract::cookie::{Cookie, Expiration, SameSite};
+
+use crate::app::common::core::SessionConfig;
+
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+pub const OIDC_FLOW_COOKIE_NAME: &str = "oidc_flow";
+pub const CSRF_TOKEN_COOKIE_NAME: &str = "csrf_token";
+pub const CSRF_TOKEN_HEADER_NAME: &str = "x-csrf-token";
+
+/// Names the configurable access token cookie must not take.
+pub const RESERVED_COOKIE_NAMES: &[&str] = &[
+    REFRESH_TOKEN_COOKIE_NAME,
+    OIDC_FLOW_COOKIE_NAME,
+    CSRF_TOKEN_COOKIE_NAME,
+];
+
+const REFRESH_TOKEN_COOKIE_PATH: &str = "/auth/refresh";
+const OIDC_FLOW_COOKIE_PATH: &str = "/auth/oidc";
+
+pub fn access_token_cookie(config: &SessionConfig, token: String) -> Cookie<'static> {
+    let expires_at = time::OffsetDateTime::now_utc()
+        + time::Duration::seconds(config.access_token_ttl.num_seconds());
+
+    session_cookie(
+        config,
+        config.cookie_name.clone(),
+        token,
+        Some(expires_at),
+        "/",
+        true,
+    )
+}
+
+/// `expires_at` is `None` for sessions without "remember me", whose cookies
+/// end with the browser session.
+pub fn refresh_token_cookie(
+    config: &SessionConfig,
+    token: String,
+    expires_at: Option<time::OffsetDateTime>,
+) -> Cookie<'static> {
+    session_cookie(
+        config,
+        REFRESH_TOKEN_COOKIE_NAME.to_string(),
+        token,
+        expires_at,
+        REFRESH_TOKEN_COOKIE_PATH,
+        true,
+    )
+}
+
+/// Readable by scripts on purpose: the admin frontend copies it into the
+/// `X-CSRF-Token` header.
+pub fn csrf_token_cookie(
+    config: &SessionConfig,
+    token: String,
+    expires_at: Option<time::OffsetDateTime>,
+) -> Cookie<'static> {
+    session_cookie(
+        config,
+        CSRF_TOKEN_COOKIE_NAME.to_string(),
+        token,
+        expires_at,
+        "/",
+        false,
+    )
+}
+
+/// Cookies that overwrite the session cookies and expire them immediately.
+pub fn cleared_session_cookies(config: &SessionConfig) -> [Cookie<'static>; 3] {
+    let mut access_cookie = access_token_cookie(config, String::new());
+    access_cookie.make_removal();
+
+    let mut refresh_cookie = refresh_token_cookie(config, String::new(), None);
+    refresh_cookie.make_removal();
+
+    let mut csrf_cookie = csrf_token_cookie(config, String::new(), None);
+    csrf_cookie.make_removal();
+
+    [access_cookie, refresh_cookie, csrf_cookie]
+}
+
+fn session_cookie(
+    config: &SessionConfig,
+    name: String,
+    value: String,
+    expires_at: Option<time::OffsetDateTime>,
+    path: &'static str,
+    http_only: bool,
+) -> Cookie<'static> {
+    let mut cookie = Cookie::build((name, value))
+        .path(path)
+        .secure(config.cookie_secure)
+        .http_only(http_only)
+        .expires(expires_at.map_or(Expiration::Session, Expiration::DateTime))
+        .same_site(config.cookie_same_site)
+        .build();
+    if let Some(domain) = &config.cookie_domain {
+        cookie.set_domain(domain.clone());
+    }
+    cookie
+}
+
+/// Lax rather than the configured policy, since it has to come along on the
+/// redirect back from the identity provider.
+pub fn oidc_flow_cookie(
+    config: &SessionConfig,
+    token: String,
+    max_age: time::Duration,
+) -> Cookie<'static> {
+    Cookie::build((OIDC_FLOW_COOKIE_NAME, token))
+        .path(OIDC_FLOW_COOKIE_PATH)
+        .secure(config.cookie_secure)
+        .http_only(true)
+        .max_age(max_age)
+        .same_site(SameSite::Lax)
+        .build()
+}
+
+pub fn cleared_oidc_flow_cookie(config: &SessionConfig) -> Cookie<'static> {
+    let mut cookie = oidc_flow_cookie(config, String::new(), time::Duration::ZERO);
+    cookie.make_removal();
+    cookie
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SessionConfig {
+        SessionConfig {
+            cookie_name: "token".to_string(),
+            cookie_secure: true,
+            cookie_same_site: SameSite::Strict,
+            cookie_domain: Some("gowithdev.in".to_string()),
+            access_token_ttl: chrono::Duration::minutes(15),
+            session_ttl: chrono::Duration::hours(24),
+            remember_me_ttl: chrono::Duration::days(30),
+        }
+    }
+
+    #[test]
+    fn refresh_token_is_only_sent_to_the_refresh_endpoint() {
+        let cookie = refresh_token_cookie(&config(), "refresh".to_string(), None);
+
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.path(), Some(REFRESH_TOKEN_COOKIE_PATH));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(cookie.domain(), Some("gowithdev.in"));
+        assert_eq!(cookie.expires(), Some(Expiration::Session));
+    }
+
+    #[test]
+    fn access_token_expires_with_the_token() {
+        let cookie = access_token_cookie(&config(), "access".to_string());
+
+        assert_eq!(cookie.name(), "token");
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.http_only(), Some(true));
+        let expires_in = cookie.expires_datetime().unwrap() - time::OffsetDateTime::now_utc();
+        assert!(expires_in <= time::Duration::minutes(15));
+        assert!(expires_in > time::Duration::minutes(14));
+    }
+
+    #[test]
+    fn csrf_token_is_readable_by_scripts() {
+        let cookie = csrf_token_cookie(&config(), "csrf".to_string(), None);
+
+        assert_eq!(cookie.name(), CSRF_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.http_only(), Some(false));
+    }
+
+    #[test]
+    fn cleared_cookies_match_the_originals() {
+        let config = config();
+
+        for cookie in cleared_session_cookies(&config) {
+            assert_eq!(cookie.value(), "");
+            assert_eq!(cookie.max_age(), Some(time::Duration::ZERO));
+            assert_eq!(cookie.domain(), Some("gowithdev.in"));
+        }
+        let [access, refresh, csrf] = cleared_session_cookies(&config);
+        assert_eq!(access.name(), "token");
+        assert_eq!(refresh.path(), Some(REFRESH_TOKEN_COOKIE_PATH));
+        assert_eq!(csrf.name(), CSRF_TOKEN_COOKIE_NAME);
+    }
+
+    #[test]
+    fn oidc_flow_cookie_survives_the_provider_redirect() {
+        let cookie = oidc_flow_cookie(&config(), "flow".to_string(), time::Duration::minutes(10));
+
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.path(), Some(OIDC_FLOW_COOKIE_PATH));
+        assert_eq!(cookie.max_age(), Some(time::Duration::minutes(10)));
+        assert_eq!(
+            cleared_oidc_flow_cookie(&config()).max_age(),
+            Some(time::Duration::ZERO)
+        );
+    }
+}
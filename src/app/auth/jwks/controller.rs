@@ -0,0 +1,17 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::Json;
+
+use crate::app::common::core::AppState;
+
+/// Publishes the public keys tokens are verified with, so other services can
+/// verify them without sharing a secret.
+pub async fn get_jwks(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(app_state.keys.jwks()),
+    )
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use jsonwebtoken::{DecodingKey, EncodingKey};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::Serialize;
+
+/// DER prefix of an Ed25519 `SubjectPublicKeyInfo`, followed by the 32 byte key.
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// Ed25519 keys used to sign and verify JWTs.
+///
+/// Keys are loaded from a directory of PEM files named `<kid>.pem`. Each file
+/// holds either a private key (PKCS#8) or, for retired keys, just the public
+/// key. Tokens are signed with the active key and verified with any of them,
+/// so a new key can be rolled out while tokens signed by the old one are
+/// still valid.
+pub struct KeyStore {
+    signing_kid: String,
+    signing_key: EncodingKey,
+    verification_keys: HashMap<String, VerificationKey>,
+}
+
+struct VerificationKey {
+    decoding_key: DecodingKey,
+    public_key: Vec<u8>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Jwk {
+    pub kty: &'static str,
+    pub crv: &'static str,
+    pub alg: &'static str,
+    #[serde(rename = "use")]
+    pub key_use: &'static str,
+    pub kid: String,
+    pub x: String,
+}
+
+impl KeyStore {
+    pub fn load(dir: &str, signing_kid: &str) -> Result<KeyStore, Box<dyn std::error::Error>> {
+        let mut signing_key = None;
+        let mut verification_keys = HashMap::new();
+
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "pem") {
+                continue;
+            }
+
+            let kid = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| format!("Invalid key file name {}", path.display()))?
+                .to_string();
+            let (label, der) = read_pem(&path)?;
+
+            let public_key = match label.as_str() {
+                "PRIVATE KEY" => {
+                    let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der)
+                        .map_err(|_| format!("{} is not an Ed25519 private key", path.display()))?;
+                    if kid == signing_kid {
+                        signing_key = Some(EncodingKey::from_ed_der(&der));
+                    }
+                    key_pair.public_key().as_ref().to_vec()
+                }
+                "PUBLIC KEY" => der
+                    .strip_prefix(&ED25519_SPKI_PREFIX[..])
+                    .filter(|key| key.len() == 32)
+                    .ok_or_else(|| format!("{} is not an Ed25519 public key", path.display()))?
+                    .to_vec(),
+                _ => return Err(format!("Unexpected PEM block in {}", path.display()).into()),
+            };
+
+            verification_keys.insert(
+                kid,
+                VerificationKey {
+                    decoding_key: DecodingKey::from_ed_der(&public_key),
+                    public_key,
+                },
+            );
+        }
+
+        let signing_key = signing_key
+            .ok_or_else(|| format!("No private key {}.pem found in {}", signing_kid, dir))?;
+
+        Ok(KeyStore {
+            signing_kid: signing_kid.to_string(),
+            signing_key,
+            verification_keys,
+        })
+    }
+
+    pub fn signing_key(&self) -> (&str, &EncodingKey) {
+        (&self.signing_kid, &self.signing_key)
+    }
+
+    pub fn decoding_key(&self, kid: &str) -> Option<&DecodingKey> {
+        self.verification_keys.get(kid).map(|key| &key.decoding_key)
+    }
+
+    pub fn jwks(&self) -> Jwks {
+        let mut keys: Vec<Jwk> = self
+            .verification_keys
+            .iter()
+            .map(|(kid, key)| Jwk {
+                kty: "OKP",
+                crv: "Ed25519",
+                alg: "EdDSA",
+                key_use: "sig",
+                kid: kid.clone(),
+                x: URL_SAFE_NO_PAD.encode(&key.public_key),
+            })
+            .collect();
+        keys.sort_by(|a, b| a.kid.cmp(&b.kid));
+
+        Jwks { keys }
+    }
+}
+
+fn read_pem(path: &Path) -> Result<(String, Vec<u8>), Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut lines = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+
+    let label = lines
+        .next()
+        .and_then(|line| line.strip_prefix("-----BEGIN "))
+        .and_then(|line| line.strip_suffix("-----"))
+        .ok_or_else(|| format!("{} is not a PEM file", path.display()))?
+        .to_string();
+
+    let body: String = lines
+        .take_while(|line| !line.starts_with("-----END "))
+        .collect();
+
+    Ok((label, STANDARD.decode(body)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use jsonwebtoken::{Algorithm, Header, Validation};
+    use ring::rand::SystemRandom;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    /// A fresh key directory, removed again when dropped.
+    struct KeyDir(PathBuf);
+
+    impl KeyDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("keys-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir(&dir).unwrap();
+            KeyDir(dir)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+
+        fn write_pem(&self, file: &str, label: &str, der: &[u8]) {
+            let body = STANDARD
+                .encode(der)
+                .as_bytes()
+                .chunks(64)
+                .map(|line| std::str::from_utf8(line).unwrap())
+                .collect::<Vec<&str>>()
+                .join("\n");
+            let pem = format!("-----BEGIN {0}-----\n{1}\n-----END {0}-----\n", label, body);
+            std::fs::write(self.0.join(file), pem).unwrap();
+        }
+
+        /// Writes a new private key and returns its PKCS#8 DER.
+        fn add_private_key(&self, kid: &str) -> Vec<u8> {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            self.write_pem(&format!("{}.pem", kid), "PRIVATE KEY", pkcs8.as_ref());
+            pkcs8.as_ref().to_vec()
+        }
+
+        /// Writes only the public half of a new key, like a retired key, and
+        /// returns the private key to sign old tokens with.
+        fn add_public_key(&self, kid: &str) -> Vec<u8> {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+            let spki = [&ED25519_SPKI_PREFIX[..], key_pair.public_key().as_ref()].concat();
+            self.write_pem(&format!("{}.pem", kid), "PUBLIC KEY", &spki);
+            pkcs8.as_ref().to_vec()
+        }
+    }
+
+    impl Drop for KeyDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims {
+            sub: "user".to_string(),
+            exp: (chrono::Utc::now().timestamp() + 60) as usize,
+        }
+    }
+
+    fn sign(kid: &str, key: &EncodingKey) -> String {
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(kid.to_string());
+        jsonwebtoken::encode(&header, &claims(), key).unwrap()
+    }
+
+    fn verify(keys: &KeyStore, token: &str) -> Option<TestClaims> {
+        let kid = jsonwebtoken::decode_header(token).ok()?.kid?;
+        let key = keys.decoding_key(&kid)?;
+        jsonwebtoken::decode(token, key, &Validation::new(Algorithm::EdDSA))
+            .ok()
+            .map(|data| data.claims)
+    }
+
+    #[test]
+    fn tokens_of_retired_keys_stay_valid() {
+        let dir = KeyDir::new();
+        dir.add_private_key("new");
+        let old = dir.add_public_key("old");
+        std::fs::write(dir.0.join("README.txt"), "not a key").unwrap();
+
+        let keys = KeyStore::load(dir.path(), "new").unwrap();
+
+        let (kid, signing_key) = keys.signing_key();
+        assert_eq!(kid, "new");
+        assert_eq!(verify(&keys, &sign(kid, signing_key)), Some(claims()));
+        assert_eq!(
+            verify(&keys, &sign("old", &EncodingKey::from_ed_der(&old))),
+            Some(claims())
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_and_mismatched_kids() {
+        let dir = KeyDir::new();
+        dir.add_private_key("new");
+        let old = dir.add_public_key("old");
+        let keys = KeyStore::load(dir.path(), "new").unwrap();
+        let old_key = EncodingKey::from_ed_der(&old);
+
+        assert_eq!(verify(&keys, &sign("gone", &old_key)), None);
+        // signed by the old key but claiming to be the new one
+        assert_eq!(verify(&keys, &sign("new", &old_key)), None);
+    }
+
+    #[test]
+    fn publishes_every_key() {
+        let dir = KeyDir::new();
+        let new = dir.add_private_key("new");
+        dir.add_public_key("old");
+        let keys = KeyStore::load(dir.path(), "new").unwrap();
+
+        let jwks = keys.jwks();
+        let kids = jwks
+            .keys
+            .iter()
+            .map(|key| key.kid.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(kids, ["new", "old"]);
+
+        let new_public = Ed25519KeyPair::from_pkcs8(&new).unwrap();
+        assert_eq!(
+            jwks.keys[0].x,
+            URL_SAFE_NO_PAD.encode(new_public.public_key().as_ref())
+        );
+        assert!(jwks
+            .keys
+            .iter()
+            .all(|key| key.kty == "OKP" && key.crv == "Ed25519" && key.alg == "EdDSA"));
+    }
+
+    #[test]
+    fn the_signing_key_needs_its_private_half() {
+        let dir = KeyDir::new();
+        dir.add_private_key("new");
+        dir.add_public_key("old");
+
+        assert!(KeyStore::load(dir.path(), "old").is_err());
+        assert!(KeyStore::load(dir.path(), "missing").is_err());
+    }
+
+    #[test]
+    fn rejects_other_pem_blocks() {
+        let dir = KeyDir::new();
+        dir.add_private_key("new");
+        dir.write_pem("cert.pem", "CERTIFICATE", b"not a certificate");
+
+        assert!(KeyStore::load(dir.path(), "new").is_err());
+    }
+}
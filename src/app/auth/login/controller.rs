@@ -1,25 +1,52 @@
+use std::net::IpAddr;
 use std::sync::Arc;
 
 use axum::extract::State;
-use axum::http::header::SET_COOKIE;
-use axum::response::{AppendHeaders, IntoResponse};
+use axum::response::{IntoResponse, Response};
 use axum::{extract::rejection::JsonRejection, http::StatusCode, Json};
-use axum_extra::extract::cookie::{Cookie, Expiration, SameSite};
 
-use crate::app::auth::model::Claims;
+use crate::app::auth::password::{hash_password, needs_rehash};
+use crate::app::auth::registration::service::email_matches;
+use crate::app::auth::secure_account::service::{
+    alert_on_new_device, send_security_alert, SecurityAlert,
+};
+use crate::app::auth::session::service::{
+    create_session, session_cookie_headers, SessionCookieHeaders,
+};
+use crate::app::auth::token::{generate_scoped_token, MFA_PENDING_AUDIENCE};
+use crate::app::common::audit::{AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::ClientInfo;
 use crate::app::common::core::AppState;
 use crate::app::common::errors::AppError;
+use crate::app::common::rate_limit::too_many_requests;
+use crate::app::orm::login_lockouts::ActiveModel as LoginLockoutActiveModel;
 use crate::app::orm::users::{Column, Entity as Users, Model as User};
+use sea_orm::sea_query::Expr;
 use sea_orm::{entity::*, query::*};
 
-use super::model::LoginRequestPayload;
+use super::model::{LoginRequestPayload, LoginResponse, MfaRequiredResponse};
+use super::throttle::LockoutSubject;
 
 const INVALID_CREDENTIALS_ERROR: &str = "Invalid email or password";
+const MFA_PENDING_TTL_MINUTES: i64 = 5;
+
+impl From<User> for LoginResponse {
+    fn from(user: User) -> Self {
+        LoginResponse {
+            id: user.id,
+            name: user.name,
+            email: user.email,
+            avatar: user.avatar,
+        }
+    }
+}
 
 pub async fn verify_login(
     State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
     payload_r: Result<Json<LoginRequestPayload>, JsonRejection>,
-) -> Result<impl IntoResponse, AppError> {
+) -> Result<Response, AppError> {
+    let client_ip = client.ip;
     let payload = payload_r.map_err(|_| AppError {
         message: "Invalid payload".to_string(),
         status: StatusCode::BAD_REQUEST,
@@ -32,9 +59,24 @@ pub async fn verify_login(
         });
     }
 
+    // checked before touching the database so throttled guesses cost no bcrypt round
+    let throttle_key = payload.email.trim().to_lowercase();
+    if let Err(retry_after) = app_state.login_throttle.check(&throttle_key, client_ip) {
+        return Ok(too_many_requests(retry_after));
+    }
+
     let user = Users::find()
-        .filter(Column::Email.eq(&payload.email))
-        .columns([Column::Email, Column::PasswordHash, Column::Id])
+        .filter(email_matches(&payload.email))
+        .columns([
+            Column::Id,
+            Column::Name,
+            Column::Email,
+            Column::PasswordHash,
+            Column::Avatar,
+            Column::Role,
+            Column::TotpEnabledAt,
+            Column::EmailVerifiedAt,
+        ])
         .one(&app_state.db)
         .await
         .map_err(|err| AppError {
@@ -43,63 +85,198 @@ pub async fn verify_login(
         })?;
 
     let user = match user {
-        Some(user) => user,
-        None => {
+        Some(user) if bcrypt::verify(&payload.password, &user.password_hash).unwrap_or(false) => {
+            user
+        }
+        _ => {
+            record_failed_login(&app_state, &throttle_key, client_ip).await?;
             return Err(AppError {
                 message: INVALID_CREDENTIALS_ERROR.to_string(),
                 status: StatusCode::UNAUTHORIZED,
-            })
+            });
         }
     };
 
-    let matched = bcrypt::verify(&payload.password, &user.password_hash).map_err(|_| AppError {
-        message: INVALID_CREDENTIALS_ERROR.to_string(),
-        status: StatusCode::UNAUTHORIZED,
-    })?;
+    app_state.login_throttle.record_success(&throttle_key);
 
-    if !matched {
-        return Err(AppError {
-            message: INVALID_CREDENTIALS_ERROR.to_string(),
-            status: StatusCode::UNAUTHORIZED,
-        });
+    if needs_rehash(&user.password_hash, app_state.config.bcrypt_cost) {
+        upgrade_password_hash(&app_state, &user, &payload.password).await;
     }
 
-    let token = generate_token(&user, &app_state)?;
-
-    let expire_time =
-        Expiration::DateTime(time::OffsetDateTime::now_utc() + time::Duration::hours(1));
-    let headers = AppendHeaders([(
-        SET_COOKIE,
-        Cookie::build(("token", token))
-            .path("/")
-            .secure(true)
-            .http_only(true)
-            .expires(expire_time)
-            .same_site(SameSite::Strict)
-            .to_string(),
-    )]);
-
-    Ok((headers, ()))
+    if user.email_verified_at.is_none() {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "Please confirm your email address before logging in".to_string(),
+        ));
+    }
+
+    continue_login(&app_state, user, &client, payload.remember_me)
+        .await
+        .map(IntoResponse::into_response)
 }
 
-fn generate_token(user: &User, app_state: &AppState) -> Result<String, AppError> {
-    let expire_time = chrono::Utc::now() + chrono::Duration::hours(1);
-    let exp: u64 = (expire_time).timestamp().unsigned_abs();
+/// Where a login stands after [`continue_login`] or [`complete_login`].
+pub enum LoginStep {
+    /// The account has a second factor, the token is exchanged at `/login/mfa`.
+    MfaRequired { mfa_token: String },
+    /// The session is started, the headers set its cookies.
+    SignedIn {
+        user: Box<User>,
+        headers: SessionCookieHeaders,
+    },
+}
 
-    let claims = Claims {
-        sub: user.id.to_string(),
-        exp: exp as usize,
-        iat: chrono::Utc::now().timestamp().unsigned_abs() as usize,
-    };
+impl IntoResponse for LoginStep {
+    fn into_response(self) -> Response {
+        match self {
+            LoginStep::MfaRequired { mfa_token } => Json(MfaRequiredResponse {
+                mfa_required: true,
+                mfa_token,
+            })
+            .into_response(),
+            LoginStep::SignedIn { user, headers } => {
+                (headers, Json(LoginResponse::from(*user))).into_response()
+            }
+        }
+    }
+}
+
+/// Called once the first factor (password, magic link, passkey or identity
+/// provider) is verified. Asks for the second factor when the account has
+/// one, otherwise starts the session.
+pub async fn continue_login(
+    app_state: &Arc<AppState>,
+    user: User,
+    client: &ClientInfo,
+    remember_me: bool,
+) -> Result<LoginStep, AppError> {
+    if user.totp_enabled_at.is_some() {
+        let mfa_token = generate_scoped_token(
+            &user.id,
+            MFA_PENDING_AUDIENCE,
+            remember_me,
+            chrono::Duration::minutes(MFA_PENDING_TTL_MINUTES),
+            app_state,
+        )?;
+
+        return Ok(LoginStep::MfaRequired { mfa_token });
+    }
+
+    complete_login(app_state, user, client, remember_me).await
+}
+
+/// Starts a session for a user who passed every login step.
+pub async fn complete_login(
+    app_state: &Arc<AppState>,
+    user: User,
+    client: &ClientInfo,
+    remember_me: bool,
+) -> Result<LoginStep, AppError> {
+    alert_on_new_device(app_state, &user, client).await?;
+
+    let issued = create_session(app_state, user.id, client, remember_me).await?;
+    let headers = session_cookie_headers(app_state, &user, issued)?;
 
-    let token = jsonwebtoken::encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &jsonwebtoken::EncodingKey::from_secret(app_state.config.jwt_secret.as_ref()),
-    );
+    AuditEvent::new(AuditAction::LoginSucceeded)
+        .actor(user.id)
+        .ip(client.ip)
+        .target(AuditTarget::User, user.id)
+        .record(&app_state.db)
+        .await;
 
-    token.map_err(|_| AppError {
-        message: "Failed to generate token".to_string(),
-        status: StatusCode::INTERNAL_SERVER_ERROR,
+    Ok(LoginStep::SignedIn {
+        user: Box::new(user),
+        headers,
     })
 }
+
+/// Re-hashes the password at the configured cost. Failures are only logged,
+/// the old hash keeps working.
+async fn upgrade_password_hash(app_state: &AppState, user: &User, password: &str) {
+    let password_hash = match hash_password(password, app_state.config.bcrypt_cost) {
+        Ok(password_hash) => password_hash,
+        Err(err) => {
+            tracing::error!("failed to upgrade password hash | {}", err.message);
+            return;
+        }
+    };
+
+    // only replaces the hash that was verified, not one changed meanwhile
+    let result = Users::update_many()
+        .col_expr(Column::PasswordHash, Expr::value(password_hash))
+        .filter(Column::Id.eq(user.id))
+        .filter(Column::PasswordHash.eq(&user.password_hash))
+        .exec(&app_state.db)
+        .await;
+
+    match result {
+        Ok(_) => tracing::info!("password hash upgraded | {}", user.id),
+        Err(err) => tracing::error!("failed to upgrade password hash | {}", err),
+    }
+}
+
+pub async fn record_failed_login(
+    app_state: &Arc<AppState>,
+    email: &str,
+    client_ip: IpAddr,
+) -> Result<(), AppError> {
+    AuditEvent::new(AuditAction::LoginFailed)
+        .ip(client_ip)
+        .target(AuditTarget::Email, email)
+        .record(&app_state.db)
+        .await;
+
+    let lockouts = app_state.login_throttle.record_failure(email, client_ip);
+
+    for lockout in lockouts {
+        let (email, ip_address) = match &lockout.subject {
+            LockoutSubject::Email(email) => (Some(email.clone()), None),
+            LockoutSubject::Ip(ip) => (None, Some(ip.to_string())),
+        };
+
+        tracing::warn!(
+            "login locked out | {:?} | {} failures | {}s",
+            lockout.subject,
+            lockout.failures,
+            lockout.duration.as_secs()
+        );
+
+        let locked_until =
+            chrono::Utc::now() + chrono::Duration::from_std(lockout.duration).unwrap_or_default();
+
+        LoginLockoutActiveModel {
+            email: ActiveValue::Set(email),
+            ip_address: ActiveValue::Set(ip_address),
+            failures: ActiveValue::Set(lockout.failures as i32),
+            locked_until: ActiveValue::Set(locked_until.into()),
+            ..Default::default()
+        }
+        .insert(&app_state.db)
+        .await?;
+
+        if let LockoutSubject::Email(email) = &lockout.subject {
+            notify_locked_out_user(app_state, email, lockout.failures).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Lets the owner know someone is guessing their password. Unknown emails
+/// get locked out all the same, there is just nobody to tell.
+async fn notify_locked_out_user(
+    app_state: &Arc<AppState>,
+    email: &str,
+    failures: u32,
+) -> Result<(), AppError> {
+    let user = Users::find()
+        .filter(email_matches(email))
+        .one(&app_state.db)
+        .await?;
+
+    if let Some(user) = user {
+        send_security_alert(app_state, &user, SecurityAlert::Lockout { failures });
+    }
+
+    Ok(())
+}
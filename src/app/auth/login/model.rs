@@ -5,6 +5,9 @@ use uuid::Uuid;
 pub struct LoginRequestPayload {
     pub email: String,
     pub password: String,
+    /// Keeps the session beyond the browser session, see `SessionConfig`.
+    #[serde(default)]
+    pub remember_me: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -14,3 +17,11 @@ pub struct LoginResponse {
     pub email: String,
     pub avatar: Option<String>,
 }
+
+/// Returned instead of the session cookies when the account has two-factor
+/// authentication enabled. The token is exchanged at `/login/mfa`.
+#[derive(Debug, Serialize)]
+pub struct MfaRequiredResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Failures older than this are forgotten.
+const FAILURE_WINDOW: Duration = Duration::from_secs(60 * 60);
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+struct ThrottlePolicy {
+    /// Failures allowed before every further attempt has to wait.
+    free_attempts: u32,
+    /// Failures after which the key is locked for `lockout`.
+    lockout_after: u32,
+    lockout: Duration,
+    max_delay: Duration,
+}
+
+const EMAIL_POLICY: ThrottlePolicy = ThrottlePolicy {
+    free_attempts: 3,
+    lockout_after: 10,
+    lockout: Duration::from_secs(15 * 60),
+    max_delay: Duration::from_secs(60),
+};
+
+// many users can share an address behind a NAT, so be more lenient per IP
+const IP_POLICY: ThrottlePolicy = ThrottlePolicy {
+    free_attempts: 20,
+    lockout_after: 100,
+    lockout: Duration::from_secs(15 * 60),
+    max_delay: Duration::from_secs(60),
+};
+
+#[derive(Debug, Clone)]
+pub enum LockoutSubject {
+    Email(String),
+    Ip(IpAddr),
+}
+
+#[derive(Debug, Clone)]
+pub struct Lockout {
+    pub subject: LockoutSubject,
+    pub failures: u32,
+    pub duration: Duration,
+}
+
+/// Tracks failed logins per email and per client IP. Each failure past the
+/// free attempts doubles the time before the next attempt is accepted, and
+/// too many failures lock the email or IP out entirely for a while.
+pub struct LoginThrottle {
+    emails: FailureTracker<String>,
+    ips: FailureTracker<IpAddr>,
+}
+
+impl Default for LoginThrottle {
+    fn default() -> Self {
+        Self {
+            emails: FailureTracker::new(EMAIL_POLICY),
+            ips: FailureTracker::new(IP_POLICY),
+        }
+    }
+}
+
+impl LoginThrottle {
+    /// Returns how long the caller has to wait before it may try again.
+    pub fn check(&self, email: &str, ip: IpAddr) -> Result<(), Duration> {
+        let wait = self
+            .emails
+            .wait_time(&email.to_string())
+            .max(self.ips.wait_time(&ip));
+
+        if wait.is_zero() {
+            Ok(())
+        } else {
+            Err(wait)
+        }
+    }
+
+    /// Records a failed attempt and returns the lockouts it triggered.
+    pub fn record_failure(&self, email: &str, ip: IpAddr) -> Vec<Lockout> {
+        let mut lockouts = Vec::new();
+
+        if let Some((failures, duration)) = self.emails.record_failure(email.to_string()) {
+            lockouts.push(Lockout {
+                subject: LockoutSubject::Email(email.to_string()),
+                failures,
+                duration,
+            });
+        }
+
+        if let Some((failures, duration)) = self.ips.record_failure(ip) {
+            lockouts.push(Lockout {
+                subject: LockoutSubject::Ip(ip),
+                failures,
+                duration,
+            });
+        }
+
+        lockouts
+    }
+
+    /// Forgets the failures of an account after a successful login. The IP
+    /// keeps its record so one valid account can not reset a password spray.
+    pub fn record_success(&self, email: &str) {
+        self.emails.clear(&email.to_string());
+    }
+}
+
+struct FailureRecord {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+struct FailureTracker<K> {
+    policy: ThrottlePolicy,
+    records: Mutex<HashMap<K, FailureRecord>>,
+}
+
+impl<K: Eq + Hash> FailureTracker<K> {
+    fn new(policy: ThrottlePolicy) -> Self {
+        Self {
+            policy,
+            records: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn wait_time(&self, key: &K) -> Duration {
+        let now = Instant::now();
+        let records = self.records.lock().unwrap();
+
+        let Some(record) = records.get(key) else {
+            return Duration::ZERO;
+        };
+
+        if let Some(locked_until) = record.locked_until {
+            if locked_until > now {
+                return locked_until - now;
+            }
+        }
+
+        if record.failures < self.policy.free_attempts {
+            return Duration::ZERO;
+        }
+
+        let exponent = (record.failures - self.policy.free_attempts).min(16);
+        let delay = Duration::from_secs(1 << exponent).min(self.policy.max_delay);
+        (record.last_failure + delay).saturating_duration_since(now)
+    }
+
+    /// Returns the failure count and lockout duration when this failure locks the key.
+    fn record_failure(&self, key: K) -> Option<(u32, Duration)> {
+        let now = Instant::now();
+        let mut records = self.records.lock().unwrap();
+
+        if records.len() >= MAX_TRACKED_KEYS {
+            records.retain(|_, record| now.duration_since(record.last_failure) < FAILURE_WINDOW);
+        }
+
+        let record = records.entry(key).or_insert(FailureRecord {
+            failures: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+
+        if now.duration_since(record.last_failure) >= FAILURE_WINDOW {
+            record.failures = 0;
+            record.locked_until = None;
+        }
+
+        record.failures += 1;
+        record.last_failure = now;
+
+        if record.failures >= self.policy.lockout_after {
+            record.locked_until = Some(now + self.policy.lockout);
+            return Some((record.failures, self.policy.lockout));
+        }
+
+        None
+    }
+
+    fn clear(&self, key: &K) {
+        self.records.lock().unwrap().remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 9));
+
+    fn assert_close(wait: Duration, expected: Duration) {
+        assert!(
+            wait <= expected && expected - wait < Duration::from_secs(1),
+            "waiting {:?}, expected {:?}",
+            wait,
+            expected
+        );
+    }
+
+    #[test]
+    fn backs_off_exponentially_after_the_free_attempts() {
+        let tracker = FailureTracker::new(EMAIL_POLICY);
+        let key = "jane@example.com".to_string();
+
+        for _ in 0..EMAIL_POLICY.free_attempts - 1 {
+            assert_eq!(tracker.record_failure(key.clone()), None);
+            assert_eq!(tracker.wait_time(&key), Duration::ZERO);
+        }
+
+        for delay in [1, 2, 4, 8, 16, 32] {
+            assert_eq!(tracker.record_failure(key.clone()), None);
+            assert_close(tracker.wait_time(&key), Duration::from_secs(delay));
+        }
+    }
+
+    #[test]
+    fn delay_is_capped() {
+        let tracker = FailureTracker::new(ThrottlePolicy {
+            lockout_after: u32::MAX,
+            ..EMAIL_POLICY
+        });
+        let key = "jane@example.com".to_string();
+
+        for _ in 0..30 {
+            tracker.record_failure(key.clone());
+        }
+        assert_close(tracker.wait_time(&key), EMAIL_POLICY.max_delay);
+    }
+
+    #[test]
+    fn locks_out_after_too_many_failures() {
+        let tracker = FailureTracker::new(EMAIL_POLICY);
+        let key = "jane@example.com".to_string();
+
+        for _ in 0..EMAIL_POLICY.lockout_after - 1 {
+            assert_eq!(tracker.record_failure(key.clone()), None);
+        }
+        assert_eq!(
+            tracker.record_failure(key.clone()),
+            Some((EMAIL_POLICY.lockout_after, EMAIL_POLICY.lockout))
+        );
+        assert_close(tracker.wait_time(&key), EMAIL_POLICY.lockout);
+    }
+
+    #[test]
+    fn success_clears_the_email_but_not_the_ip() {
+        let throttle = LoginThrottle::default();
+
+        for _ in 0..EMAIL_POLICY.free_attempts {
+            throttle.record_failure("jane@example.com", IP);
+        }
+        assert!(throttle.check("jane@example.com", IP).is_err());
+        assert!(throttle.check("john@example.com", IP).is_ok());
+
+        throttle.record_success("jane@example.com");
+        assert!(throttle.check("jane@example.com", IP).is_ok());
+        assert_eq!(
+            throttle.ips.records.lock().unwrap()[&IP].failures,
+            EMAIL_POLICY.free_attempts
+        );
+    }
+
+    #[test]
+    fn reports_email_and_ip_lockouts() {
+        let throttle = LoginThrottle::default();
+
+        let lockouts = (0..EMAIL_POLICY.lockout_after)
+            .flat_map(|_| throttle.record_failure("jane@example.com", IP))
+            .collect::<Vec<Lockout>>();
+        assert_eq!(lockouts.len(), 1);
+        assert!(matches!(
+            &lockouts[0].subject,
+            LockoutSubject::Email(email) if email == "jane@example.com"
+        ));
+
+        let lockouts = (0..IP_POLICY.lockout_after)
+            .flat_map(|attempt| {
+                throttle.record_failure(&format!("user{}@example.com", attempt), IP)
+            })
+            .collect::<Vec<Lockout>>();
+        assert!(lockouts
+            .iter()
+            .any(|lockout| matches!(lockout.subject, LockoutSubject::Ip(ip) if ip == IP)));
+    }
+}
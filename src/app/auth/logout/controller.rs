@@ -0,0 +1,88 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header::SET_COOKIE;
+use axum::http::StatusCode;
+use axum::response::{AppendHeaders, IntoResponse};
+use axum::Extension;
+use axum_extra::extract::CookieJar;
+use uuid::Uuid;
+
+use crate::app::auth::cookies::cleared_session_cookies;
+use crate::app::auth::model::UserInfo;
+use crate::app::auth::session::service::{
+    revoke_session, revoke_user_sessions, SessionCookieHeaders,
+};
+use crate::app::auth::token::decode_expired_access_token;
+use crate::app::common::audit::{AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::ClientIp;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+
+pub async fn logout(
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AppError> {
+    let claims = jar
+        .get(&app_state.config.session.cookie_name)
+        .and_then(|cookie| decode_expired_access_token(cookie.value(), &app_state));
+
+    if let Some(claims) = claims {
+        app_state
+            .revocations
+            .revoke_token(&app_state.db, &claims)
+            .await?;
+
+        if let Ok(session_id) = Uuid::from_str(&claims.sid) {
+            revoke_session(&app_state, session_id).await?;
+        }
+
+        if let Ok(user_id) = Uuid::from_str(&claims.sub) {
+            AuditEvent::new(AuditAction::TokenRevoked)
+                .actor(user_id)
+                .ip(client_ip)
+                .target(AuditTarget::Session, &claims.sid)
+                .record(&app_state.db)
+                .await;
+        }
+    }
+
+    Ok((StatusCode::NO_CONTENT, clear_cookie_headers(&app_state)))
+}
+
+pub async fn logout_everywhere(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+) -> Result<impl IntoResponse, AppError> {
+    user_info.ensure_session()?;
+
+    let user_id = Uuid::from_str(&user_info.id).unwrap();
+
+    app_state
+        .revocations
+        .revoke_all_for_user(&app_state.db, user_id)
+        .await?;
+    revoke_user_sessions(&app_state, user_id).await?;
+
+    AuditEvent::new(AuditAction::TokenRevoked)
+        .actor(user_id)
+        .ip(client_ip)
+        .target(AuditTarget::User, user_id)
+        .record(&app_state.db)
+        .await;
+
+    Ok((StatusCode::NO_CONTENT, clear_cookie_headers(&app_state)))
+}
+
+fn clear_cookie_headers(app_state: &AppState) -> SessionCookieHeaders {
+    let cookies = cleared_session_cookies(&app_state.config.session);
+    AppendHeaders(
+        cookies
+            .into_iter()
+            .map(|cookie| (SET_COOKIE, cookie.to_string()))
+            .collect(),
+    )
+}
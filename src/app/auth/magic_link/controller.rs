@@ -0,0 +1,171 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::json;
+
+use crate::app::auth::login::controller::continue_login;
+use crate::app::auth::model::UserInfo;
+use crate::app::auth::registration::service::email_matches;
+use crate::app::auth::token::{decode_scoped_token, generate_scoped_token, MAGIC_LINK_AUDIENCE};
+use crate::app::auth::user_token::{consume_user_token, store_user_token, UserTokenPurpose};
+use crate::app::common::audit::{AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::{ClientInfo, ClientIp};
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::common::mailer::Email;
+use crate::app::orm::users::{Column, Entity as Users};
+
+use super::model::{MagicLinkRequest, PasswordlessSetting, VerifyMagicLinkRequest};
+
+const MAGIC_LINK_TTL_MINUTES: i64 = 15;
+const INVALID_MAGIC_LINK_ERROR: &str = "Invalid or expired login link";
+
+/// Always answers `202 Accepted`, like `forgot_password`, so the response
+/// does not reveal which addresses have passwordless login enabled.
+pub async fn request_magic_link(
+    State(app_state): State<Arc<AppState>>,
+    payload_r: Result<Json<MagicLinkRequest>, JsonRejection>,
+) -> Result<StatusCode, AppError> {
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let email = payload.email.trim().to_string();
+    if email.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Email is required".to_string(),
+        ));
+    }
+
+    tokio::spawn(async move {
+        if let Err(err) = send_magic_link(&app_state, &email, payload.remember_me).await {
+            tracing::error!("failed to send magic link | {}", err.message);
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Redeems a magic link. The token must carry a valid signature and still be
+/// unused; accounts with two-factor authentication continue at `/login/mfa`.
+pub async fn verify_magic_link(
+    State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
+    payload_r: Result<Json<VerifyMagicLinkRequest>, JsonRejection>,
+) -> Result<Response, AppError> {
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let invalid_link = || {
+        AppError::new(
+            StatusCode::UNAUTHORIZED,
+            INVALID_MAGIC_LINK_ERROR.to_string(),
+        )
+    };
+
+    let claims = decode_scoped_token(&payload.token, MAGIC_LINK_AUDIENCE, &app_state)
+        .ok_or_else(invalid_link)?;
+
+    let user_id = consume_user_token(&app_state, &payload.token, UserTokenPurpose::MagicLink)
+        .await?
+        .ok_or_else(invalid_link)?;
+
+    // the setting may have been turned off after the link was sent
+    let user = Users::find_by_id(user_id)
+        .one(&app_state.db)
+        .await?
+        .filter(|user| user.passwordless_enabled)
+        .ok_or_else(invalid_link)?;
+
+    continue_login(&app_state, user, &client, claims.remember_me)
+        .await
+        .map(IntoResponse::into_response)
+}
+
+pub async fn update_passwordless(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+    Json(payload): Json<PasswordlessSetting>,
+) -> Result<Json<PasswordlessSetting>, AppError> {
+    user_info.ensure_session()?;
+    let user_id = Uuid::from_str(&user_info.id).unwrap();
+
+    let updated = Users::update_many()
+        .col_expr(Column::PasswordlessEnabled, Expr::value(payload.enabled))
+        .filter(Column::Id.eq(user_id))
+        .filter(Column::PasswordlessEnabled.ne(payload.enabled))
+        .exec(&app_state.db)
+        .await?;
+
+    if updated.rows_affected > 0 {
+        AuditEvent::new(AuditAction::Updated)
+            .actor(user_id)
+            .ip(client_ip)
+            .target(AuditTarget::User, user_id)
+            .changes(json!({
+                "passwordless_enabled": { "from": !payload.enabled, "to": payload.enabled }
+            }))
+            .record(&app_state.db)
+            .await;
+    }
+
+    Ok(Json(payload))
+}
+
+async fn send_magic_link(
+    app_state: &AppState,
+    email: &str,
+    remember_me: bool,
+) -> Result<(), AppError> {
+    let user = Users::find()
+        .filter(email_matches(email))
+        .filter(Column::PasswordlessEnabled.eq(true))
+        .one(&app_state.db)
+        .await?;
+
+    let Some(user) = user else {
+        return Ok(());
+    };
+
+    if app_state.email_quota.check(&user.email).is_err() {
+        tracing::warn!("email quota exceeded | user {}", user.id);
+        return Ok(());
+    }
+
+    let ttl = chrono::Duration::minutes(MAGIC_LINK_TTL_MINUTES);
+    let token = generate_scoped_token(&user.id, MAGIC_LINK_AUDIENCE, remember_me, ttl, app_state)?;
+    store_user_token(app_state, user.id, UserTokenPurpose::MagicLink, &token, ttl).await?;
+
+    let login_url = format!(
+        "{}/magic-link?token={}",
+        app_state.config.app_base_url, token
+    );
+
+    app_state
+        .mailer
+        .send(Email {
+            to: user.email,
+            subject: "Your login link".to_string(),
+            body: format!(
+                "Hi {},\n\n\
+                Use the link below to sign in. It expires in {} minutes \
+                and can only be used once.\n\n{}\n\n\
+                If you did not ask for this, you can ignore this email.",
+                user.name, MAGIC_LINK_TTL_MINUTES, login_url
+            ),
+        })
+        .await
+}
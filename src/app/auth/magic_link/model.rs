@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct MagicLinkRequest {
+    pub email: String,
+    /// Remembered in the link, the session starts when it is opened.
+    #[serde(default)]
+    pub remember_me: bool,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyMagicLinkRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PasswordlessSetting {
+    pub enabled: bool,
+}
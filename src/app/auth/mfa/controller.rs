@@ -0,0 +1,244 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait};
+
+use crate::app::auth::login::controller::{complete_login, record_failed_login};
+use crate::app::auth::model::UserInfo;
+use crate::app::auth::token::{decode_scoped_token, MFA_PENDING_AUDIENCE};
+use crate::app::auth::totp::{generate_secret, provisioning_uri, verify_code};
+use crate::app::common::audit::{diff, AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::{ClientInfo, ClientIp};
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::common::rate_limit::too_many_requests;
+use crate::app::orm::users::{ActiveModel as UserActiveModel, Entity as Users, Model as User};
+
+use super::model::{
+    DisableMfaRequest, MfaCodeRequest, MfaLoginRequest, MfaSetupResponse, RecoveryCodesResponse,
+};
+use super::service::{
+    claim_totp_step, delete_recovery_codes, generate_recovery_codes, verify_second_factor,
+};
+
+const INVALID_CODE_ERROR: &str = "Invalid code";
+
+/// Second login step: exchanges the token returned by `/login` and a TOTP or
+/// recovery code for the session cookies.
+pub async fn verify_login_mfa(
+    State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
+    payload_r: Result<Json<MfaLoginRequest>, JsonRejection>,
+) -> Result<Response, AppError> {
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let claims = decode_scoped_token(&payload.mfa_token, MFA_PENDING_AUDIENCE, &app_state)
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::UNAUTHORIZED,
+                "Login expired, please sign in again".to_string(),
+            )
+        })?;
+
+    let user = Users::find_by_id(Uuid::from_str(&claims.sub).unwrap_or_default())
+        .one(&app_state.db)
+        .await?
+        .filter(|user| user.totp_enabled_at.is_some())
+        .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, INVALID_CODE_ERROR.to_string()))?;
+
+    // codes are guessable too, so they share the password throttle
+    let throttle_key = user.email.to_lowercase();
+    let client_ip = client.ip;
+    if let Err(retry_after) = app_state.login_throttle.check(&throttle_key, client_ip) {
+        return Ok(too_many_requests(retry_after));
+    }
+
+    if !verify_second_factor(&app_state, &user, &payload.code).await? {
+        record_failed_login(&app_state, &throttle_key, client_ip).await?;
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            INVALID_CODE_ERROR.to_string(),
+        ));
+    }
+
+    app_state.login_throttle.record_success(&throttle_key);
+
+    complete_login(&app_state, user, &client, claims.remember_me)
+        .await
+        .map(IntoResponse::into_response)
+}
+
+/// Generates a new secret for the user. It only takes effect once a code
+/// generated from it is confirmed through `enable_mfa`.
+pub async fn setup_mfa(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+) -> Result<Json<MfaSetupResponse>, AppError> {
+    user_info.ensure_session()?;
+    let user = find_user(&app_state, &user_info).await?;
+
+    if user.totp_enabled_at.is_some() {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+
+    let secret = generate_secret();
+    let provisioning_uri = provisioning_uri(&secret, &user.email);
+
+    let mut user: UserActiveModel = user.into();
+    user.totp_secret = ActiveValue::Set(Some(secret.clone()));
+    user.update(&app_state.db).await?;
+
+    Ok(Json(MfaSetupResponse {
+        secret,
+        provisioning_uri,
+    }))
+}
+
+pub async fn enable_mfa(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    user_info.ensure_session()?;
+    let user = find_user(&app_state, &user_info).await?;
+
+    let secret = match (&user.totp_secret, user.totp_enabled_at) {
+        (Some(secret), None) => secret.clone(),
+        (_, Some(_)) => {
+            return Err(AppError::new(
+                StatusCode::CONFLICT,
+                "Two-factor authentication is already enabled".to_string(),
+            ))
+        }
+        (None, None) => {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Two-factor authentication has not been set up".to_string(),
+            ))
+        }
+    };
+
+    let now = chrono::Utc::now();
+    let step = verify_code(
+        &secret,
+        &payload.code,
+        now.timestamp().unsigned_abs(),
+        user.totp_last_used_step,
+    )
+    .ok_or_else(|| AppError::new(StatusCode::BAD_REQUEST, INVALID_CODE_ERROR.to_string()))?;
+
+    if !claim_totp_step(&app_state, user.id, step).await? {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            INVALID_CODE_ERROR.to_string(),
+        ));
+    }
+
+    let user_id = user.id;
+    let before = user.clone();
+    let mut user: UserActiveModel = user.into();
+    user.totp_enabled_at = ActiveValue::Set(Some(DateTimeWithTimeZone::from(now)));
+    let user = user.update(&app_state.db).await?;
+
+    record_user_update(&app_state, &before, &user, client_ip).await;
+
+    let recovery_codes = generate_recovery_codes(&app_state, user_id).await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+pub async fn disable_mfa(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+    Json(payload): Json<DisableMfaRequest>,
+) -> Result<StatusCode, AppError> {
+    user_info.ensure_session()?;
+    let user = find_enabled_user(&app_state, &user_info).await?;
+
+    let password_matches = bcrypt::verify(&payload.password, &user.password_hash).unwrap_or(false);
+    if !password_matches || !verify_second_factor(&app_state, &user, &payload.code).await? {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "Invalid password or code".to_string(),
+        ));
+    }
+
+    let user_id = user.id;
+    let before = user.clone();
+    let mut user: UserActiveModel = user.into();
+    user.totp_secret = ActiveValue::Set(None);
+    user.totp_enabled_at = ActiveValue::Set(None);
+    user.totp_last_used_step = ActiveValue::Set(None);
+    let user = user.update(&app_state.db).await?;
+
+    record_user_update(&app_state, &before, &user, client_ip).await;
+
+    delete_recovery_codes(&app_state, user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn regenerate_recovery_codes(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    user_info.ensure_session()?;
+    let user = find_enabled_user(&app_state, &user_info).await?;
+
+    if !verify_second_factor(&app_state, &user, &payload.code).await? {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            INVALID_CODE_ERROR.to_string(),
+        ));
+    }
+
+    let recovery_codes = generate_recovery_codes(&app_state, user.id).await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+async fn find_user(app_state: &AppState, user_info: &UserInfo) -> Result<User, AppError> {
+    Users::find_by_id(Uuid::from_str(&user_info.id).unwrap())
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "User not found".to_string()))
+}
+
+async fn find_enabled_user(app_state: &AppState, user_info: &UserInfo) -> Result<User, AppError> {
+    let user = find_user(app_state, user_info).await?;
+
+    if user.totp_enabled_at.is_none() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Two-factor authentication is not enabled".to_string(),
+        ));
+    }
+
+    Ok(user)
+}
+
+async fn record_user_update(app_state: &AppState, before: &User, after: &User, client_ip: IpAddr) {
+    AuditEvent::new(AuditAction::Updated)
+        .actor(after.id)
+        .ip(client_ip)
+        .target(AuditTarget::User, after.id)
+        .changes(diff(Some(before), Some(after)))
+        .record(&app_state.db)
+        .await;
+}
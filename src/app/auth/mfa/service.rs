@@ -0,0 +1,129 @@
+use data_encoding::BASE32_NOPAD;
+use rand::RngCore;
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::{Condition, Expr};
+use sea_orm::{ActiveValue, ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::app::auth::token::hash_token;
+use crate::app::auth::totp::verify_code;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::mfa_recovery_codes::{
+    ActiveModel as RecoveryCodeActiveModel, Column as RecoveryCodeColumn, Entity as RecoveryCodes,
+};
+use crate::app::orm::users::{Column as UserColumn, Entity as Users, Model as User};
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Checks a second factor, either a TOTP code or an unused recovery code.
+/// Both can only be redeemed once.
+pub async fn verify_second_factor(
+    app_state: &AppState,
+    user: &User,
+    code: &str,
+) -> Result<bool, AppError> {
+    if let Some(secret) = &user.totp_secret {
+        let now = chrono::Utc::now().timestamp().unsigned_abs();
+        if let Some(step) = verify_code(secret, code, now, user.totp_last_used_step) {
+            return claim_totp_step(app_state, user.id, step).await;
+        }
+    }
+
+    redeem_recovery_code(app_state, user.id, code).await
+}
+
+/// Records `step` as used so a code that was seen once (e.g. shoulder surfed
+/// or phished) can not be replayed within its validity window.
+pub async fn claim_totp_step(
+    app_state: &AppState,
+    user_id: Uuid,
+    step: u64,
+) -> Result<bool, AppError> {
+    let step = step as i64;
+
+    let claimed = Users::update_many()
+        .col_expr(UserColumn::TotpLastUsedStep, Expr::value(step))
+        .filter(UserColumn::Id.eq(user_id))
+        .filter(
+            Condition::any()
+                .add(UserColumn::TotpLastUsedStep.is_null())
+                .add(UserColumn::TotpLastUsedStep.lt(step)),
+        )
+        .exec(&app_state.db)
+        .await?;
+
+    Ok(claimed.rows_affected == 1)
+}
+
+/// Replaces the user's recovery codes and returns the new ones in plain text.
+pub async fn generate_recovery_codes(
+    app_state: &AppState,
+    user_id: Uuid,
+) -> Result<Vec<String>, AppError> {
+    RecoveryCodes::delete_many()
+        .filter(RecoveryCodeColumn::UserId.eq(user_id))
+        .exec(&app_state.db)
+        .await?;
+
+    let codes = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect::<Vec<String>>();
+
+    let models = codes.iter().map(|code| RecoveryCodeActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        code_hash: ActiveValue::Set(hash_token(&normalize_recovery_code(code))),
+        ..Default::default()
+    });
+
+    RecoveryCodes::insert_many(models)
+        .exec(&app_state.db)
+        .await?;
+
+    Ok(codes)
+}
+
+pub async fn delete_recovery_codes(app_state: &AppState, user_id: Uuid) -> Result<(), AppError> {
+    RecoveryCodes::delete_many()
+        .filter(RecoveryCodeColumn::UserId.eq(user_id))
+        .exec(&app_state.db)
+        .await?;
+
+    Ok(())
+}
+
+async fn redeem_recovery_code(
+    app_state: &AppState,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, AppError> {
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+    let redeemed = RecoveryCodes::update_many()
+        .col_expr(RecoveryCodeColumn::UsedAt, Expr::value(now))
+        .filter(RecoveryCodeColumn::UserId.eq(user_id))
+        .filter(RecoveryCodeColumn::CodeHash.eq(hash_token(&normalize_recovery_code(code))))
+        .filter(RecoveryCodeColumn::UsedAt.is_null())
+        .exec(&app_state.db)
+        .await?;
+
+    if redeemed.rows_affected > 0 {
+        tracing::info!("recovery code used | user {}", user_id);
+    }
+
+    Ok(redeemed.rows_affected > 0)
+}
+
+/// Codes look like `ABCDE-FGHIJ`.
+fn generate_recovery_code() -> String {
+    let mut bytes = [0u8; 7];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let encoded = BASE32_NOPAD.encode(&bytes);
+    format!("{}-{}", &encoded[..5], &encoded[5..10])
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_uppercase()
+}
@@ -1,2 +1,21 @@
+pub mod cookies;
+pub mod jwks;
+pub mod keys;
 pub mod login;
+pub mod logout;
+pub mod magic_link;
+pub mod mfa;
 pub mod model;
+pub mod oidc;
+pub mod passkey;
+pub mod password;
+pub mod password_reset;
+pub mod personal_access_token;
+pub mod registration;
+pub mod revocation;
+pub mod secure_account;
+pub mod session;
+pub mod token;
+pub mod totp;
+pub mod user_token;
+pub mod webauthn;
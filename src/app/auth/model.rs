@@ -1,6 +1,48 @@
+use std::str::FromStr;
+
+use axum::http::StatusCode;
+use sea_orm::prelude::Uuid;
+use serde::{Deserialize, Serialize};
+
+use crate::app::common::errors::AppError;
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Claims {
     pub sub: String,
+    pub sid: String,
+    pub jti: String,
+    pub role: Role,
+    /// Hash of the session's CSRF token, see `middleware::verify_csrf_token`.
+    #[serde(default)]
+    pub csrf: Option<String>,
+    pub exp: usize,
+    pub iat: usize,
+}
+
+/// Claims of single purpose tokens such as the one issued between the
+/// password and the second factor of a login.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct ScopedClaims {
+    pub sub: String,
+    pub aud: String,
+    pub jti: String,
+    /// Carried along to the step that finally starts the session.
+    #[serde(default)]
+    pub remember_me: bool,
+    pub exp: usize,
+    pub iat: usize,
+}
+
+/// State of an OpenID Connect login kept in a signed cookie between the
+/// redirect to the provider and the callback.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct OidcFlowClaims {
+    pub aud: String,
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    #[serde(default)]
+    pub remember_me: bool,
     pub exp: usize,
     pub iat: usize,
 }
@@ -8,4 +50,247 @@ pub struct Claims {
 #[derive(Clone)]
 pub struct UserInfo {
     pub id: String,
+    pub role: Role,
+    pub auth_method: AuthMethod,
+    /// Set when authenticated with a personal access token, which can only use
+    /// the scopes it was created with.
+    pub scopes: Option<Vec<Permission>>,
+    pub csrf_token_hash: Option<String>,
+    /// The login session behind the access token, `None` for personal access
+    /// tokens.
+    pub session_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    Cookie,
+    BearerToken,
+    PersonalAccessToken,
+}
+
+impl UserInfo {
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.role.has_permission(permission)
+            && self
+                .scopes
+                .as_ref()
+                .is_none_or(|scopes| scopes.contains(&permission))
+    }
+
+    /// Account security settings can only be changed from a login session,
+    /// never with a personal access token.
+    pub fn ensure_session(&self) -> Result<(), AppError> {
+        if self.auth_method == AuthMethod::PersonalAccessToken {
+            return Err(AppError::new(
+                StatusCode::FORBIDDEN,
+                "Not allowed with a personal access token".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// A user without scopes or a CSRF token, with a login session unless
+    /// authenticated with a personal access token.
+    #[cfg(test)]
+    pub fn for_tests(role: Role, auth_method: AuthMethod) -> UserInfo {
+        UserInfo {
+            id: Uuid::new_v4().to_string(),
+            role,
+            auth_method,
+            scopes: None,
+            csrf_token_hash: None,
+            session_id: (auth_method != AuthMethod::PersonalAccessToken).then(Uuid::new_v4),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Editor,
+    Author,
+    Contributor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    PostsRead,
+    PostsWrite,
+    PostsWriteOthers,
+    PostsPublish,
+    PostsPurge,
+    CategoriesRead,
+    CategoriesWrite,
+    MediaUpload,
+    UsersManage,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Editor => "editor",
+            Role::Author => "author",
+            Role::Contributor => "contributor",
+        }
+    }
+
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Admin => &[
+                Permission::PostsRead,
+                Permission::PostsWrite,
+                Permission::PostsWriteOthers,
+                Permission::PostsPublish,
+                Permission::PostsPurge,
+                Permission::CategoriesRead,
+                Permission::CategoriesWrite,
+                Permission::MediaUpload,
+                Permission::UsersManage,
+            ],
+            Role::Editor => &[
+                Permission::PostsRead,
+                Permission::PostsWrite,
+                Permission::PostsWriteOthers,
+                Permission::PostsPublish,
+                Permission::PostsPurge,
+                Permission::CategoriesRead,
+                Permission::CategoriesWrite,
+                Permission::MediaUpload,
+            ],
+            Role::Author => &[
+                Permission::PostsRead,
+                Permission::PostsWrite,
+                Permission::PostsPublish,
+                Permission::CategoriesRead,
+                Permission::MediaUpload,
+            ],
+            Role::Contributor => &[
+                Permission::PostsRead,
+                Permission::PostsWrite,
+                Permission::CategoriesRead,
+                Permission::MediaUpload,
+            ],
+        }
+    }
+
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "admin" => Ok(Role::Admin),
+            "editor" => Ok(Role::Editor),
+            "author" => Ok(Role::Author),
+            "contributor" => Ok(Role::Contributor),
+            _ => Err(format!("Unknown role: {}", role)),
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = String;
+
+    fn from_str(permission: &str) -> Result<Self, Self::Err> {
+        match permission {
+            "posts:read" => Ok(Permission::PostsRead),
+            "posts:write" => Ok(Permission::PostsWrite),
+            "posts:write_others" => Ok(Permission::PostsWriteOthers),
+            "posts:publish" => Ok(Permission::PostsPublish),
+            "posts:purge" => Ok(Permission::PostsPurge),
+            "categories:read" => Ok(Permission::CategoriesRead),
+            "categories:write" => Ok(Permission::CategoriesWrite),
+            "media:upload" => Ok(Permission::MediaUpload),
+            "users:manage" => Ok(Permission::UsersManage),
+            _ => Err(format!("Unknown permission: {}", permission)),
+        }
+    }
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::PostsRead => "posts:read",
+            Permission::PostsWrite => "posts:write",
+            Permission::PostsWriteOthers => "posts:write_others",
+            Permission::PostsPublish => "posts:publish",
+            Permission::PostsPurge => "posts:purge",
+            Permission::CategoriesRead => "categories:read",
+            Permission::CategoriesWrite => "categories:write",
+            Permission::MediaUpload => "media:upload",
+            Permission::UsersManage => "users:manage",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROLES: [Role; 4] = [Role::Admin, Role::Editor, Role::Author, Role::Contributor];
+
+    #[test]
+    fn each_role_includes_the_permissions_of_the_roles_below() {
+        for pair in ROLES.windows(2) {
+            let (higher, lower) = (pair[0], pair[1]);
+            for permission in lower.permissions() {
+                assert!(
+                    higher.has_permission(*permission),
+                    "{} lacks {} of {}",
+                    higher.as_str(),
+                    permission.as_str(),
+                    lower.as_str()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn only_admins_manage_users() {
+        assert!(Role::Admin.has_permission(Permission::UsersManage));
+        for role in &ROLES[1..] {
+            assert!(!role.has_permission(Permission::UsersManage));
+        }
+    }
+
+    #[test]
+    fn authors_and_contributors_cannot_touch_other_posts() {
+        for role in [Role::Author, Role::Contributor] {
+            assert!(role.has_permission(Permission::PostsWrite));
+            assert!(!role.has_permission(Permission::PostsWriteOthers));
+            assert!(!role.has_permission(Permission::PostsPurge));
+            assert!(!role.has_permission(Permission::CategoriesWrite));
+        }
+        assert!(Role::Author.has_permission(Permission::PostsPublish));
+        assert!(!Role::Contributor.has_permission(Permission::PostsPublish));
+    }
+
+    #[test]
+    fn roles_and_permissions_round_trip_through_strings() {
+        for role in ROLES {
+            assert_eq!(Role::from_str(role.as_str()), Ok(role));
+        }
+        for permission in Role::Admin.permissions() {
+            assert_eq!(Permission::from_str(permission.as_str()), Ok(*permission));
+        }
+
+        assert!(Role::from_str("Admin").is_err());
+        assert!(Permission::from_str("posts:*").is_err());
+    }
+
+    #[test]
+    fn roles_serialize_lowercase() {
+        assert_eq!(serde_json::to_string(&Role::Editor).unwrap(), "\"editor\"");
+        assert_eq!(
+            serde_json::from_str::<Role>("\"contributor\"").unwrap(),
+            Role::Contributor
+        );
+    }
 }
@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::header::SET_COOKIE;
+use axum::http::StatusCode;
+use axum::response::{AppendHeaders, IntoResponse, Redirect, Response};
+use axum_extra::extract::CookieJar;
+use sea_orm::{EntityTrait, QueryFilter};
+
+use crate::app::auth::cookies::{
+    cleared_oidc_flow_cookie, oidc_flow_cookie, OIDC_FLOW_COOKIE_NAME,
+};
+use crate::app::auth::login::controller::{continue_login, LoginStep};
+use crate::app::auth::password::hash_password;
+use crate::app::auth::registration::service::{create_user, email_matches, NewUser};
+use crate::app::auth::token::{
+    decode_oidc_flow_token, generate_oidc_flow_token, generate_opaque_token,
+};
+use crate::app::common::client_ip::ClientInfo;
+use crate::app::common::core::{AppState, OidcConfig};
+use crate::app::common::errors::AppError;
+use crate::app::orm::users::{Entity as Users, Model as User};
+
+use super::model::{CallbackParams, IdTokenClaims, LoginParams};
+use super::service::{pkce_challenge, OidcClient};
+
+const OIDC_FLOW_TTL_MINUTES: i64 = 10;
+const INVALID_LOGIN_ERROR: &str = "Login with the identity provider failed";
+
+/// Redirects to the provider's authorization endpoint. The state, nonce and
+/// PKCE verifier stay with the browser in a signed cookie.
+pub async fn start_oidc_login(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<LoginParams>,
+) -> Result<Response, AppError> {
+    let (config, client) = oidc(&app_state)?;
+    let provider = client.provider(config).await?;
+
+    let state = generate_opaque_token();
+    let nonce = generate_opaque_token();
+    let code_verifier = generate_opaque_token();
+    let code_challenge = pkce_challenge(&code_verifier);
+
+    let ttl = chrono::Duration::minutes(OIDC_FLOW_TTL_MINUTES);
+    let flow_token = generate_oidc_flow_token(
+        state.clone(),
+        nonce.clone(),
+        code_verifier,
+        params.remember_me,
+        ttl,
+        &app_state,
+    )?;
+
+    let authorization_url = url::Url::parse_with_params(
+        &provider.metadata.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", config.redirect_url.as_str()),
+            ("scope", config.scopes.as_str()),
+            ("state", state.as_str()),
+            ("nonce", nonce.as_str()),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|_| {
+        AppError::new(
+            StatusCode::BAD_GATEWAY,
+            "Identity provider is misconfigured".to_string(),
+        )
+    })?;
+
+    let cookie = oidc_flow_cookie(
+        &app_state.config.session,
+        flow_token,
+        time::Duration::minutes(OIDC_FLOW_TTL_MINUTES),
+    );
+
+    Ok((
+        AppendHeaders([(SET_COOKIE, cookie.to_string())]),
+        Redirect::to(authorization_url.as_str()),
+    )
+        .into_response())
+}
+
+/// Handles the provider's redirect back: checks the state, redeems the code,
+/// signs the matching user in and sends the browser on to the frontend. Users
+/// with two-factor authentication are sent to the frontend's code prompt
+/// instead, with an `mfa_token` like `/login` returns.
+pub async fn oidc_callback(
+    State(app_state): State<Arc<AppState>>,
+    client_info: ClientInfo,
+    jar: CookieJar,
+    Query(params): Query<CallbackParams>,
+) -> Result<Response, AppError> {
+    let (config, client) = oidc(&app_state)?;
+    let failed_login = || AppError::new(StatusCode::UNAUTHORIZED, INVALID_LOGIN_ERROR.to_string());
+
+    if let Some(error) = &params.error {
+        tracing::warn!(
+            "oidc login denied by provider | {} | {}",
+            error,
+            params.error_description.as_deref().unwrap_or_default()
+        );
+        return Err(failed_login());
+    }
+
+    let flow = jar
+        .get(OIDC_FLOW_COOKIE_NAME)
+        .and_then(|cookie| decode_oidc_flow_token(cookie.value(), &app_state))
+        .ok_or_else(failed_login)?;
+
+    let (Some(code), Some(state)) = (&params.code, &params.state) else {
+        return Err(failed_login());
+    };
+    if *state != flow.state {
+        tracing::warn!("oidc login state mismatch");
+        return Err(failed_login());
+    }
+
+    let claims = client
+        .exchange_code(config, code, &flow.code_verifier, &flow.nonce)
+        .await?;
+
+    let user = find_or_create_user(&app_state, config, claims).await?;
+
+    // the provider only stands in for the password, a second factor is still required
+    let response = match continue_login(&app_state, user, &client_info, flow.remember_me).await? {
+        LoginStep::MfaRequired { mfa_token } => {
+            // in the fragment so it never reaches a server log, the frontend
+            // posts it to /login/mfa together with the code
+            Redirect::to(&format!(
+                "{}/login/mfa#mfa_token={}",
+                app_state.config.app_base_url, mfa_token
+            ))
+            .into_response()
+        }
+        LoginStep::SignedIn { headers, .. } => {
+            (headers, Redirect::to(&app_state.config.app_base_url)).into_response()
+        }
+    };
+
+    Ok((
+        AppendHeaders([(
+            SET_COOKIE,
+            cleared_oidc_flow_cookie(&app_state.config.session).to_string(),
+        )]),
+        response,
+    )
+        .into_response())
+}
+
+/// Maps the ID token to a user by email, creating the user on first login.
+/// Only emails the provider explicitly marks as verified are trusted for the
+/// mapping, a missing `email_verified` claim is refused like a false one.
+async fn find_or_create_user(
+    app_state: &AppState,
+    config: &OidcConfig,
+    claims: IdTokenClaims,
+) -> Result<User, AppError> {
+    let email = claims
+        .email
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| !email.is_empty())
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::FORBIDDEN,
+                "The identity provider did not share an email address".to_string(),
+            )
+        })?;
+
+    if claims.email_verified != Some(true) {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "The email address is not verified by the identity provider".to_string(),
+        ));
+    }
+
+    let user = Users::find()
+        .filter(email_matches(&email))
+        .one(&app_state.db)
+        .await?;
+
+    if let Some(user) = user {
+        return Ok(user);
+    }
+
+    let name = claims
+        .name
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_string());
+
+    // the account can only sign in through the provider until a password is set
+    let password_hash = hash_password(&generate_opaque_token(), app_state.config.bcrypt_cost)?;
+
+    let user = create_user(
+        app_state,
+        NewUser {
+            name,
+            email,
+            password_hash,
+            role: config.default_role,
+            // checked by the provider, see above
+            email_verified_at: Some(chrono::Utc::now().into()),
+        },
+    )
+    .await?;
+
+    tracing::info!("user linked to oidc subject | {} | {}", user.id, claims.sub);
+
+    Ok(user)
+}
+
+fn oidc(app_state: &AppState) -> Result<(&OidcConfig, &OidcClient), AppError> {
+    match (&app_state.config.oidc, &app_state.oidc) {
+        (Some(config), Some(client)) => Ok((config, client)),
+        _ => Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            "OpenID Connect login is not enabled".to_string(),
+        )),
+    }
+}
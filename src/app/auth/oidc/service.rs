@@ -0,0 +1,472 @@
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use sha2::{Digest, Sha256};
+
+use crate::app::common::core::OidcConfig;
+use crate::app::common::errors::AppError;
+
+use super::model::{IdTokenClaims, ProviderMetadata, TokenResponse};
+
+/// How long the discovery document and signing keys are reused before they
+/// are fetched again. An unknown `kid` triggers an early refresh.
+const PROVIDER_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Signature algorithms accepted for ID tokens. Symmetric ones are left out
+/// on purpose, the client secret must never double as a verification key.
+const ID_TOKEN_ALGORITHMS: [Algorithm; 8] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::EdDSA,
+];
+
+/// Talks to the configured OpenID Connect provider. Discovery happens on the
+/// first login rather than at startup, so the API does not depend on the
+/// provider being reachable to boot.
+pub struct OidcClient {
+    http: reqwest::Client,
+    provider: RwLock<Option<Arc<Provider>>>,
+}
+
+pub struct Provider {
+    pub metadata: ProviderMetadata,
+    jwks: JwkSet,
+    fetched_at: Instant,
+}
+
+impl OidcClient {
+    pub fn new() -> Result<OidcClient, reqwest::Error> {
+        let http = reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?;
+
+        Ok(OidcClient {
+            http,
+            provider: RwLock::new(None),
+        })
+    }
+
+    pub async fn provider(&self, config: &OidcConfig) -> Result<Arc<Provider>, AppError> {
+        let cached = self.provider.read().unwrap().clone();
+        match cached {
+            Some(provider) if provider.fetched_at.elapsed() < PROVIDER_CACHE_TTL => Ok(provider),
+            _ => self.refresh_provider(config).await,
+        }
+    }
+
+    /// Exchanges the authorization code (with the PKCE verifier) for an ID
+    /// token and returns its validated claims.
+    pub async fn exchange_code(
+        &self,
+        config: &OidcConfig,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, AppError> {
+        let provider = self.provider(config).await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", config.redirect_url.as_str()),
+            ("client_id", config.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(client_secret) = &config.client_secret {
+            form.push(("client_secret", client_secret.as_str()));
+        }
+
+        let response = self
+            .http
+            .post(&provider.metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(provider_error)?;
+
+        if !response.status().is_success() {
+            tracing::warn!("oidc token exchange failed | {}", response.status());
+            return Err(AppError::new(
+                StatusCode::UNAUTHORIZED,
+                "Login with the identity provider failed".to_string(),
+            ));
+        }
+
+        let tokens: TokenResponse = response.json().await.map_err(provider_error)?;
+
+        self.validate_id_token(config, provider, &tokens.id_token, nonce)
+            .await
+    }
+
+    async fn validate_id_token(
+        &self,
+        config: &OidcConfig,
+        provider: Arc<Provider>,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, AppError> {
+        let invalid_token =
+            || AppError::new(StatusCode::UNAUTHORIZED, "Invalid ID token".to_string());
+
+        let header = jsonwebtoken::decode_header(id_token).map_err(|_| invalid_token())?;
+        if !ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+            return Err(invalid_token());
+        }
+        let kid = header.kid.ok_or_else(invalid_token)?;
+
+        // the provider may have rotated its keys since we cached them
+        let provider = match provider.jwks.find(&kid) {
+            Some(_) => provider,
+            None => self.refresh_provider(config).await?,
+        };
+        let jwk = provider.jwks.find(&kid).ok_or_else(invalid_token)?;
+        let key = DecodingKey::from_jwk(jwk).map_err(|_| invalid_token())?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&config.client_id]);
+        validation.set_issuer(&[&provider.metadata.issuer]);
+        validation.set_required_spec_claims(&["exp", "iat", "iss", "aud", "sub"]);
+
+        let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|err| {
+                tracing::warn!("oidc id token rejected | {}", err);
+                invalid_token()
+            })?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            tracing::warn!("oidc id token nonce mismatch | {}", claims.sub);
+            return Err(invalid_token());
+        }
+
+        Ok(claims)
+    }
+
+    async fn refresh_provider(&self, config: &OidcConfig) -> Result<Arc<Provider>, AppError> {
+        let discovery_url = format!("{}/.well-known/openid-configuration", config.issuer);
+        let metadata: ProviderMetadata = self.get_json(&discovery_url).await?;
+
+        if metadata.issuer.trim_end_matches('/') != config.issuer {
+            tracing::error!(
+                "oidc discovery issuer mismatch | expected {} | got {}",
+                config.issuer,
+                metadata.issuer
+            );
+            return Err(AppError::new(
+                StatusCode::BAD_GATEWAY,
+                "Identity provider is misconfigured".to_string(),
+            ));
+        }
+
+        let jwks: JwkSet = self.get_json(&metadata.jwks_uri).await?;
+
+        let provider = Arc::new(Provider {
+            metadata,
+            jwks,
+            fetched_at: Instant::now(),
+        });
+        *self.provider.write().unwrap() = Some(provider.clone());
+
+        Ok(provider)
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, AppError> {
+        self.http
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)
+    }
+}
+
+/// The S256 PKCE challenge sent with the authorization request for `verifier`.
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn provider_error(err: reqwest::Error) -> AppError {
+    tracing::error!("oidc provider request failed | {}", err);
+    AppError::new(
+        StatusCode::BAD_GATEWAY,
+        "Identity provider is unavailable".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use axum::extract::{Form, State};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use jsonwebtoken::{EncodingKey, Header};
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::app::auth::model::Role;
+
+    const CLIENT_ID: &str = "gowithdev";
+    const REDIRECT_URL: &str = "https://api.example.com/auth/oidc/callback";
+    const KID: &str = "mock-key";
+    const CODE: &str = "authorization-code";
+    const CODE_VERIFIER: &str = "code-verifier-with-enough-entropy-0123456789";
+    const NONCE: &str = "nonce-0123456789";
+
+    /// Minimal provider: discovery document, JWKS and a token endpoint that
+    /// checks the PKCE verifier and hands out whatever ID token the test set.
+    struct MockProvider {
+        issuer: String,
+        advertised_issuer: String,
+        jwks: Value,
+        signing_key: EncodingKey,
+        code_challenge: String,
+        id_token: Mutex<String>,
+        discoveries: AtomicUsize,
+    }
+
+    impl MockProvider {
+        async fn start(advertised_issuer: Option<&str>) -> (Arc<MockProvider>, OidcConfig) {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let issuer = format!("http://{}", listener.local_addr().unwrap());
+
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+
+            let provider = Arc::new(MockProvider {
+                advertised_issuer: advertised_issuer.unwrap_or(&issuer).to_string(),
+                issuer: issuer.clone(),
+                jwks: json!({
+                    "keys": [{
+                        "kty": "OKP",
+                        "crv": "Ed25519",
+                        "alg": "EdDSA",
+                        "use": "sig",
+                        "kid": KID,
+                        "x": URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+                    }]
+                }),
+                signing_key: EncodingKey::from_ed_der(pkcs8.as_ref()),
+                code_challenge: pkce_challenge(CODE_VERIFIER),
+                id_token: Mutex::new(String::new()),
+                discoveries: AtomicUsize::new(0),
+            });
+
+            let router = Router::new()
+                .route("/.well-known/openid-configuration", get(discovery))
+                .route("/jwks", get(jwks))
+                .route("/token", post(token))
+                .with_state(provider.clone());
+            tokio::spawn(async move { axum::serve(listener, router).await });
+
+            let config = OidcConfig {
+                issuer,
+                client_id: CLIENT_ID.to_string(),
+                client_secret: None,
+                redirect_url: REDIRECT_URL.to_string(),
+                scopes: "openid email profile".to_string(),
+                default_role: Role::Author,
+            };
+
+            (provider, config)
+        }
+
+        fn claims(&self) -> Value {
+            let now = chrono::Utc::now().timestamp();
+            json!({
+                "iss": self.issuer,
+                "aud": CLIENT_ID,
+                "sub": "subject-1",
+                "email": "Jane@Example.com",
+                "email_verified": true,
+                "nonce": NONCE,
+                "iat": now,
+                "exp": now + 300,
+            })
+        }
+
+        fn issue(&self, header: Header, claims: &Value, key: &EncodingKey) {
+            *self.id_token.lock().unwrap() = jsonwebtoken::encode(&header, claims, key).unwrap();
+        }
+
+        fn issue_signed(&self, claims: &Value) {
+            let mut header = Header::new(Algorithm::EdDSA);
+            header.kid = Some(KID.to_string());
+            self.issue(header, claims, &self.signing_key);
+        }
+    }
+
+    async fn discovery(State(provider): State<Arc<MockProvider>>) -> Json<Value> {
+        provider.discoveries.fetch_add(1, Ordering::SeqCst);
+        Json(json!({
+            "issuer": provider.advertised_issuer,
+            "authorization_endpoint": format!("{}/authorize", provider.issuer),
+            "token_endpoint": format!("{}/token", provider.issuer),
+            "jwks_uri": format!("{}/jwks", provider.issuer),
+        }))
+    }
+
+    async fn jwks(State(provider): State<Arc<MockProvider>>) -> Json<Value> {
+        Json(provider.jwks.clone())
+    }
+
+    async fn token(
+        State(provider): State<Arc<MockProvider>>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Result<Json<Value>, StatusCode> {
+        let field = |name: &str| form.get(name).map(String::as_str);
+        let valid = field("grant_type") == Some("authorization_code")
+            && field("code") == Some(CODE)
+            && field("client_id") == Some(CLIENT_ID)
+            && field("redirect_uri") == Some(REDIRECT_URL)
+            && field("code_verifier").map(pkce_challenge) == Some(provider.code_challenge.clone());
+        if !valid {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        Ok(Json(json!({
+            "access_token": "unused",
+            "token_type": "Bearer",
+            "id_token": *provider.id_token.lock().unwrap(),
+        })))
+    }
+
+    async fn exchange(
+        config: &OidcConfig,
+        code_verifier: &str,
+    ) -> Result<IdTokenClaims, StatusCode> {
+        OidcClient::new()
+            .unwrap()
+            .exchange_code(config, CODE, code_verifier, NONCE)
+            .await
+            .map_err(|err| err.status)
+    }
+
+    #[test]
+    fn pkce_challenge_matches_rfc_7636_example() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[tokio::test]
+    async fn exchanges_code_and_validates_id_token() {
+        let (provider, config) = MockProvider::start(None).await;
+        provider.issue_signed(&provider.claims());
+
+        let client = OidcClient::new().unwrap();
+        let claims = client
+            .exchange_code(&config, CODE, CODE_VERIFIER, NONCE)
+            .await
+            .map_err(|err| err.status)
+            .unwrap();
+        assert_eq!(claims.sub, "subject-1");
+        assert_eq!(claims.email.as_deref(), Some("Jane@Example.com"));
+        assert_eq!(claims.email_verified, Some(true));
+
+        // discovery and keys are cached between logins
+        client
+            .exchange_code(&config, CODE, CODE_VERIFIER, NONCE)
+            .await
+            .map_err(|err| err.status)
+            .unwrap();
+        assert_eq!(provider.discoveries.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_pkce_verifier() {
+        let (provider, config) = MockProvider::start(None).await;
+        provider.issue_signed(&provider.claims());
+
+        let result = exchange(&config, "some-other-verifier").await;
+        assert_eq!(result.unwrap_err(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn rejects_discovery_for_another_issuer() {
+        let (provider, config) = MockProvider::start(Some("https://evil.example.com")).await;
+        provider.issue_signed(&provider.claims());
+
+        let result = exchange(&config, CODE_VERIFIER).await;
+        assert_eq!(result.unwrap_err(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_id_token_claims() {
+        let (provider, config) = MockProvider::start(None).await;
+        let now = chrono::Utc::now().timestamp();
+
+        let cases = [
+            ("nonce", json!("replayed-nonce")),
+            ("nonce", Value::Null),
+            ("aud", json!("another-client")),
+            ("iss", json!("https://evil.example.com")),
+            ("exp", json!(now - 600)),
+        ];
+        for (claim, value) in cases {
+            let mut claims = provider.claims();
+            claims[claim] = value.clone();
+            provider.issue_signed(&claims);
+
+            let result = exchange(&config, CODE_VERIFIER).await;
+            assert_eq!(
+                result.unwrap_err(),
+                StatusCode::UNAUTHORIZED,
+                "{} = {}",
+                claim,
+                value
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_id_token_not_signed_by_provider_key() {
+        let (provider, config) = MockProvider::start(None).await;
+        let claims = provider.claims();
+
+        // unknown kid, even after refetching the keys
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some("rotated-away".to_string());
+        provider.issue(header, &claims, &provider.signing_key);
+        assert_eq!(
+            exchange(&config, CODE_VERIFIER).await.unwrap_err(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // right kid, but signed by somebody else's key
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(KID.to_string());
+        provider.issue(header, &claims, &EncodingKey::from_ed_der(pkcs8.as_ref()));
+        assert_eq!(
+            exchange(&config, CODE_VERIFIER).await.unwrap_err(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // symmetric algorithms are never accepted
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(KID.to_string());
+        provider.issue(header, &claims, &EncodingKey::from_secret(b"client-secret"));
+        assert_eq!(
+            exchange(&config, CODE_VERIFIER).await.unwrap_err(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+use crate::app::auth::login::controller::{complete_login, continue_login};
+use crate::app::auth::model::UserInfo;
+use crate::app::auth::webauthn::{
+    encode_credential_id, verify_assertion, verify_client_data, verify_registration, ALG_EDDSA,
+    ALG_ES256,
+};
+use crate::app::common::audit::{diff, AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::{ClientInfo, ClientIp};
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::common::rate_limit::too_many_requests;
+use crate::app::orm::users::Entity as Users;
+use crate::app::orm::webauthn_credentials::{
+    ActiveModel as CredentialActiveModel, Column as CredentialColumn, Entity as Credentials,
+    Model as Credential,
+};
+
+use super::model::{
+    AuthenticationCredential, AuthenticatorSelection, CreationOptions, CredentialDescriptor,
+    CredentialParameters, PasskeyResponse, RegisterPasskeyRequest, RelyingPartyEntity,
+    RequestOptions, UserEntity,
+};
+use super::service::{
+    consume_challenge, is_sign_count_regression, issue_challenge, CeremonyKind,
+    CEREMONY_TIMEOUT_SECS,
+};
+
+const RP_NAME: &str = "GoWithDev";
+const PUBLIC_KEY_TYPE: &str = "public-key";
+const DEFAULT_PASSKEY_NAME: &str = "Passkey";
+const MAX_PASSKEY_NAME_LEN: usize = 100;
+const INVALID_PASSKEY_ERROR: &str = "Passkey could not be verified";
+
+impl From<Credential> for PasskeyResponse {
+    fn from(credential: Credential) -> Self {
+        PasskeyResponse {
+            id: credential.id,
+            name: credential.name,
+            created_at: credential.created_at,
+            last_used_at: credential.last_used_at,
+        }
+    }
+}
+
+pub async fn registration_options(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+) -> Result<Json<CreationOptions>, AppError> {
+    user_info.ensure_session()?;
+    let user_id = Uuid::from_str(&user_info.id).unwrap();
+
+    let user = Users::find_by_id(user_id)
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "User not found".to_string()))?;
+
+    let exclude_credentials = Credentials::find()
+        .filter(CredentialColumn::UserId.eq(user_id))
+        .all(&app_state.db)
+        .await?
+        .into_iter()
+        .map(|credential| CredentialDescriptor {
+            kind: PUBLIC_KEY_TYPE,
+            id: credential.credential_id,
+        })
+        .collect();
+
+    let challenge = issue_challenge(&app_state, Some(user_id), CeremonyKind::Registration).await?;
+
+    Ok(Json(CreationOptions {
+        challenge,
+        rp: RelyingPartyEntity {
+            id: app_state.config.webauthn_rp_id.clone(),
+            name: RP_NAME,
+        },
+        user: UserEntity {
+            id: URL_SAFE_NO_PAD.encode(user.id.as_bytes()),
+            name: user.email,
+            display_name: user.name,
+        },
+        pub_key_cred_params: [ALG_ES256, ALG_EDDSA]
+            .into_iter()
+            .map(|alg| CredentialParameters {
+                kind: PUBLIC_KEY_TYPE,
+                alg,
+            })
+            .collect(),
+        timeout: CEREMONY_TIMEOUT_SECS as u64 * 1000,
+        attestation: "none",
+        exclude_credentials,
+        // discoverable credentials let the login start without an email
+        authenticator_selection: AuthenticatorSelection {
+            resident_key: "required",
+            require_resident_key: true,
+            user_verification: "preferred",
+        },
+    }))
+}
+
+pub async fn register_passkey(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+    payload_r: Result<Json<RegisterPasskeyRequest>, JsonRejection>,
+) -> Result<Json<PasskeyResponse>, AppError> {
+    user_info.ensure_session()?;
+    let user_id = Uuid::from_str(&user_info.id).unwrap();
+
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let name = match payload.name.trim() {
+        "" => DEFAULT_PASSKEY_NAME,
+        name if name.chars().count() > MAX_PASSKEY_NAME_LEN => {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Passkey name must be at most {} characters",
+                    MAX_PASSKEY_NAME_LEN
+                ),
+            ))
+        }
+        name => name,
+    };
+
+    let response = payload.credential.response;
+    let client_data_json = decode_field(&response.client_data_json)?;
+    let attestation_object = decode_field(&response.attestation_object)?;
+
+    let challenge = verify_client_data(
+        &client_data_json,
+        "webauthn.create",
+        &app_state.config.webauthn_origin,
+    )
+    .map_err(rejected_passkey)?;
+
+    if !consume_challenge(
+        &app_state,
+        &challenge,
+        Some(user_id),
+        CeremonyKind::Registration,
+    )
+    .await?
+    {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            INVALID_PASSKEY_ERROR.to_string(),
+        ));
+    }
+
+    let (authenticator_data, credential) =
+        verify_registration(&app_state.config.webauthn_rp_id, &attestation_object)
+            .map_err(rejected_passkey)?;
+
+    let credential_id = encode_credential_id(&credential.credential_id);
+    let existing = Credentials::find()
+        .filter(CredentialColumn::CredentialId.eq(&credential_id))
+        .one(&app_state.db)
+        .await?;
+
+    if existing.is_some() {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "This passkey is already registered".to_string(),
+        ));
+    }
+
+    let passkey = CredentialActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        credential_id: ActiveValue::Set(credential_id),
+        public_key: ActiveValue::Set(credential.public_key),
+        algorithm: ActiveValue::Set(credential.algorithm as i32),
+        sign_count: ActiveValue::Set(authenticator_data.sign_count as i64),
+        name: ActiveValue::Set(name.to_string()),
+        ..Default::default()
+    }
+    .insert(&app_state.db)
+    .await?;
+
+    let passkey = PasskeyResponse::from(passkey);
+    AuditEvent::new(AuditAction::Created)
+        .actor(user_id)
+        .ip(client_ip)
+        .target(AuditTarget::Passkey, passkey.id)
+        .changes(diff(None, Some(&passkey)))
+        .record(&app_state.db)
+        .await;
+
+    Ok(Json(passkey))
+}
+
+pub async fn get_passkeys(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+) -> Result<Json<Vec<PasskeyResponse>>, AppError> {
+    user_info.ensure_session()?;
+
+    let passkeys = Credentials::find()
+        .filter(CredentialColumn::UserId.eq(Uuid::from_str(&user_info.id).unwrap()))
+        .order_by_desc(CredentialColumn::CreatedAt)
+        .all(&app_state.db)
+        .await?;
+
+    Ok(Json(passkeys.into_iter().map(Into::into).collect()))
+}
+
+pub async fn delete_passkey(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    user_info.ensure_session()?;
+    let user_id = Uuid::from_str(&user_info.id).unwrap();
+
+    let not_found = || AppError::new(StatusCode::NOT_FOUND, "Passkey not found".to_string());
+
+    let passkey = Credentials::find_by_id(id)
+        .filter(CredentialColumn::UserId.eq(user_id))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(not_found)?;
+
+    let deleted = Credentials::delete_by_id(passkey.id)
+        .exec(&app_state.db)
+        .await?;
+
+    if deleted.rows_affected == 0 {
+        return Err(not_found());
+    }
+
+    AuditEvent::new(AuditAction::Deleted)
+        .actor(user_id)
+        .ip(client_ip)
+        .target(AuditTarget::Passkey, passkey.id)
+        .changes(diff(Some(&PasskeyResponse::from(passkey)), None))
+        .record(&app_state.db)
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Unauthenticated and writes a challenge row, so limited per client IP.
+pub async fn login_options(
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+) -> Result<Response, AppError> {
+    if let Err(retry_after) = app_state
+        .passkey_challenge_quota
+        .check(&client_ip.to_string())
+    {
+        tracing::warn!("passkey challenge quota exceeded | {}", client_ip);
+        return Ok(too_many_requests(retry_after));
+    }
+
+    let challenge = issue_challenge(&app_state, None, CeremonyKind::Authentication).await?;
+
+    Ok(Json(RequestOptions {
+        challenge,
+        rp_id: app_state.config.webauthn_rp_id.clone(),
+        timeout: CEREMONY_TIMEOUT_SECS as u64 * 1000,
+        user_verification: "preferred",
+        allow_credentials: Vec::new(),
+    })
+    .into_response())
+}
+
+/// Ends the login ceremony with the same cookies `verify_login` sets. A
+/// passkey with user verification counts as both factors; otherwise accounts
+/// with two-factor authentication still have to enter a code.
+pub async fn verify_passkey_login(
+    State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
+    payload_r: Result<Json<AuthenticationCredential>, JsonRejection>,
+) -> Result<Response, AppError> {
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let response = payload.response;
+    let client_data_json = decode_field(&response.client_data_json)?;
+    let authenticator_data = decode_field(&response.authenticator_data)?;
+    let signature = decode_field(&response.signature)?;
+
+    let invalid_passkey =
+        || AppError::new(StatusCode::UNAUTHORIZED, INVALID_PASSKEY_ERROR.to_string());
+
+    let challenge = verify_client_data(
+        &client_data_json,
+        "webauthn.get",
+        &app_state.config.webauthn_origin,
+    )
+    .map_err(|_| invalid_passkey())?;
+
+    if !consume_challenge(&app_state, &challenge, None, CeremonyKind::Authentication).await? {
+        return Err(invalid_passkey());
+    }
+
+    let credential = Credentials::find()
+        .filter(CredentialColumn::CredentialId.eq(&payload.id))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(invalid_passkey)?;
+
+    if let Some(user_handle) = &response.user_handle {
+        if *user_handle != URL_SAFE_NO_PAD.encode(credential.user_id.as_bytes()) {
+            return Err(invalid_passkey());
+        }
+    }
+
+    let verified = verify_assertion(
+        &app_state.config.webauthn_rp_id,
+        &authenticator_data,
+        &client_data_json,
+        &signature,
+        credential.algorithm as i64,
+        &credential.public_key,
+    )
+    .map_err(|err| {
+        tracing::warn!("passkey login rejected | {} | {}", credential.id, err);
+        invalid_passkey()
+    })?;
+
+    let sign_count = verified.sign_count as i64;
+    if is_sign_count_regression(credential.sign_count, sign_count) {
+        tracing::warn!(
+            "passkey sign count went backwards, possibly cloned | {}",
+            credential.id
+        );
+        return Err(invalid_passkey());
+    }
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    Credentials::update_many()
+        .col_expr(CredentialColumn::SignCount, Expr::value(sign_count))
+        .col_expr(CredentialColumn::LastUsedAt, Expr::value(now))
+        .filter(CredentialColumn::Id.eq(credential.id))
+        .exec(&app_state.db)
+        .await?;
+
+    let user = Users::find_by_id(credential.user_id)
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(invalid_passkey)?;
+
+    let step = if verified.user_verified() {
+        complete_login(&app_state, user, &client, payload.remember_me).await?
+    } else {
+        continue_login(&app_state, user, &client, payload.remember_me).await?
+    };
+
+    Ok(step.into_response())
+}
+
+fn decode_field(value: &str) -> Result<Vec<u8>, AppError> {
+    URL_SAFE_NO_PAD.decode(value).map_err(|_| {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Invalid credential encoding".to_string(),
+        )
+    })
+}
+
+fn rejected_passkey(err: crate::app::auth::webauthn::WebauthnError) -> AppError {
+    AppError::new(
+        StatusCode::BAD_REQUEST,
+        format!("{}: {}", INVALID_PASSKEY_ERROR, err),
+    )
+}
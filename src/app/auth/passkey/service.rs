@@ -0,0 +1,111 @@
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::app::auth::token::generate_opaque_token;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::webauthn_challenges::{
+    ActiveModel as ChallengeActiveModel, Column as ChallengeColumn, Entity as Challenges,
+};
+
+pub const CEREMONY_TIMEOUT_SECS: i64 = 5 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CeremonyKind {
+    Registration,
+    Authentication,
+}
+
+impl CeremonyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CeremonyKind::Registration => "registration",
+            CeremonyKind::Authentication => "authentication",
+        }
+    }
+}
+
+/// Whether the counter an authenticator reported is not ahead of the stored
+/// one, which hints at a cloned credential. Authenticators without a counter
+/// always report 0 and are let through.
+pub fn is_sign_count_regression(stored: i64, reported: i64) -> bool {
+    (reported != 0 || stored != 0) && reported <= stored
+}
+
+/// Stores a random challenge for one ceremony. Registration challenges are
+/// bound to the user starting it; login challenges to nobody, since the
+/// credential tells us who is signing in.
+pub async fn issue_challenge(
+    app_state: &AppState,
+    user_id: Option<Uuid>,
+    kind: CeremonyKind,
+) -> Result<String, AppError> {
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+    // opportunistic cleanup of abandoned ceremonies
+    Challenges::delete_many()
+        .filter(ChallengeColumn::ExpiresAt.lt(now))
+        .exec(&app_state.db)
+        .await?;
+
+    let challenge = generate_opaque_token();
+
+    ChallengeActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        purpose: ActiveValue::Set(kind.as_str().to_string()),
+        challenge: ActiveValue::Set(challenge.clone()),
+        expires_at: ActiveValue::Set(now + chrono::Duration::seconds(CEREMONY_TIMEOUT_SECS)),
+        ..Default::default()
+    }
+    .insert(&app_state.db)
+    .await?;
+
+    Ok(challenge)
+}
+
+/// Deletes the challenge and reports whether it was still valid, so every
+/// challenge can complete at most one ceremony.
+pub async fn consume_challenge(
+    app_state: &AppState,
+    challenge: &str,
+    user_id: Option<Uuid>,
+    kind: CeremonyKind,
+) -> Result<bool, AppError> {
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+    let user_filter = match user_id {
+        Some(user_id) => ChallengeColumn::UserId.eq(user_id),
+        None => ChallengeColumn::UserId.is_null(),
+    };
+
+    let deleted = Challenges::delete_many()
+        .filter(ChallengeColumn::Challenge.eq(challenge))
+        .filter(ChallengeColumn::Purpose.eq(kind.as_str()))
+        .filter(ChallengeColumn::ExpiresAt.gt(now))
+        .filter(user_filter)
+        .exec(&app_state.db)
+        .await?;
+
+    Ok(deleted.rows_affected == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_count_has_to_move_forward() {
+        assert!(!is_sign_count_regression(0, 1));
+        assert!(!is_sign_count_regression(41, 42));
+        assert!(!is_sign_count_regression(5, 1000));
+
+        assert!(is_sign_count_regression(42, 42));
+        assert!(is_sign_count_regression(42, 41));
+        assert!(is_sign_count_regression(42, 0));
+    }
+
+    #[test]
+    fn authenticators_without_counter_always_pass() {
+        assert!(!is_sign_count_regression(0, 0));
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use axum::http::StatusCode;
+
+use crate::app::common::core::Config;
+use crate::app::common::errors::AppError;
+
+/// bcrypt ignores everything past 72 bytes.
+pub const MAX_PASSWORD_BYTES: usize = 72;
+
+/// Rules new passwords have to follow, built once at startup from `Config`.
+pub struct PasswordPolicy {
+    min_length: usize,
+    min_character_classes: usize,
+    /// Lowercased entries of the breached password list.
+    breached: HashSet<String>,
+}
+
+impl PasswordPolicy {
+    pub fn load(config: &Config) -> Result<PasswordPolicy, Box<dyn std::error::Error>> {
+        let breached = match std::fs::read_to_string(&config.breached_passwords_file) {
+            Ok(contents) => contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_lowercase)
+                .collect(),
+            Err(err) if config.breached_passwords_required => {
+                return Err(
+                    format!("Failed to read {}: {}", config.breached_passwords_file, err).into(),
+                )
+            }
+            Err(_) => {
+                tracing::warn!(
+                    "breached password list {} not found, skipping the check",
+                    config.breached_passwords_file
+                );
+                HashSet::new()
+            }
+        };
+
+        Ok(PasswordPolicy {
+            min_length: config.password_min_length,
+            min_character_classes: config.password_min_character_classes,
+            breached,
+        })
+    }
+
+    pub fn validate(&self, password: &str) -> Result<(), AppError> {
+        let bad_request = |message: String| AppError::new(StatusCode::BAD_REQUEST, message);
+
+        if password.chars().count() < self.min_length {
+            return Err(bad_request(format!(
+                "Password must be at least {} characters long",
+                self.min_length
+            )));
+        }
+
+        if password.len() > MAX_PASSWORD_BYTES {
+            return Err(bad_request(format!(
+                "Password must be at most {} bytes long",
+                MAX_PASSWORD_BYTES
+            )));
+        }
+
+        if character_classes(password) < self.min_character_classes {
+            return Err(bad_request(format!(
+                "Password must mix at least {} of lowercase letters, uppercase letters, digits and symbols",
+                self.min_character_classes
+            )));
+        }
+
+        if self.breached.contains(&password.to_lowercase()) {
+            return Err(bad_request(
+                "This password has appeared in a data breach, please choose another one"
+                    .to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub fn hash_password(password: &str, cost: u32) -> Result<String, AppError> {
+    bcrypt::hash(password, cost).map_err(|_| AppError {
+        message: "Failed to hash password".to_string(),
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+    })
+}
+
+/// Whether `hash` was made with a lower cost than the configured one and
+/// should be replaced the next time the plain password is known.
+pub fn needs_rehash(hash: &str, cost: u32) -> bool {
+    bcrypt::HashParts::from_str(hash).is_ok_and(|parts| parts.get_cost() < cost)
+}
+
+fn character_classes(password: &str) -> usize {
+    let checks: [fn(&char) -> bool; 3] = [
+        char::is_ascii_lowercase,
+        char::is_ascii_uppercase,
+        char::is_ascii_digit,
+    ];
+
+    let mut classes = checks
+        .iter()
+        .filter(|check| password.chars().any(|c| check(&c)))
+        .count();
+    if password.chars().any(|c| !c.is_ascii_alphanumeric()) {
+        classes += 1;
+    }
+
+    classes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(min_length: usize, min_character_classes: usize) -> PasswordPolicy {
+        PasswordPolicy {
+            min_length,
+            min_character_classes,
+            breached: HashSet::from(["password123".to_string()]),
+        }
+    }
+
+    fn rejects(policy: &PasswordPolicy, password: &str) -> bool {
+        policy
+            .validate(password)
+            .is_err_and(|err| err.status == StatusCode::BAD_REQUEST)
+    }
+
+    #[test]
+    fn length_is_counted_in_characters() {
+        let policy = policy(8, 1);
+
+        assert!(rejects(&policy, "short"));
+        assert!(policy.validate("longenough").is_ok());
+        // 4 characters, 8 bytes
+        assert!(rejects(&policy, "éééé"));
+        assert!(policy.validate("éééééééé").is_ok());
+    }
+
+    #[test]
+    fn rejects_passwords_bcrypt_would_truncate() {
+        let policy = policy(8, 1);
+
+        assert!(policy.validate(&"a".repeat(MAX_PASSWORD_BYTES)).is_ok());
+        assert!(rejects(&policy, &"a".repeat(MAX_PASSWORD_BYTES + 1)));
+        assert!(rejects(&policy, &"é".repeat(MAX_PASSWORD_BYTES / 2 + 1)));
+    }
+
+    #[test]
+    fn counts_character_classes() {
+        assert_eq!(character_classes("lowercase"), 1);
+        assert_eq!(character_classes("MixedCase"), 2);
+        assert_eq!(character_classes("Mixed1Case"), 3);
+        assert_eq!(character_classes("Mixed1Case!"), 4);
+        assert_eq!(character_classes("ümlaut"), 2);
+
+        let policy = policy(8, 3);
+        assert!(rejects(&policy, "MixedCase"));
+        assert!(policy.validate("Mixed1Case").is_ok());
+    }
+
+    #[test]
+    fn rejects_breached_passwords_in_any_case() {
+        let policy = policy(8, 1);
+
+        assert!(rejects(&policy, "password123"));
+        assert!(rejects(&policy, "PassWord123"));
+        assert!(policy.validate("password1234").is_ok());
+    }
+
+    #[test]
+    fn rehashes_only_weaker_hashes() {
+        let hash = hash_password("correct horse", 4).unwrap_or_default();
+
+        assert!(bcrypt::verify("correct horse", &hash).unwrap());
+        assert!(needs_rehash(&hash, 5));
+        assert!(!needs_rehash(&hash, 4));
+        assert!(!needs_rehash("not a bcrypt hash", 12));
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::app::auth::password::hash_password;
+use crate::app::auth::registration::service::email_matches;
+use crate::app::auth::session::service::revoke_user_sessions;
+use crate::app::auth::user_token::{
+    claim_user_token, find_user_token, issue_user_token, UserTokenPurpose,
+};
+use crate::app::common::audit::{redacted_change, AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::ClientIp;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::common::mailer::Email;
+use crate::app::orm::users::{Column, Entity as Users, Model as User};
+
+use super::model::{ForgotPasswordRequest, ResetPasswordRequest};
+
+const RESET_TOKEN_TTL_MINUTES: i64 = 60;
+const INVALID_RESET_TOKEN_ERROR: &str = "Invalid or expired reset token";
+
+/// Always answers `202 Accepted`. The lookup and the email happen in the
+/// background so neither the status nor the timing reveals whether the
+/// address belongs to an account.
+pub async fn forgot_password(
+    State(app_state): State<Arc<AppState>>,
+    payload_r: Result<Json<ForgotPasswordRequest>, JsonRejection>,
+) -> Result<StatusCode, AppError> {
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let email = payload.email.trim().to_string();
+    if email.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Email is required".to_string(),
+        ));
+    }
+
+    tokio::spawn(async move {
+        if let Err(err) = send_reset_email(&app_state, &email).await {
+            tracing::error!("failed to send password reset email | {}", err.message);
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+pub async fn reset_password(
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    payload_r: Result<Json<ResetPasswordRequest>, JsonRejection>,
+) -> Result<StatusCode, AppError> {
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let invalid_token = || {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            INVALID_RESET_TOKEN_ERROR.to_string(),
+        )
+    };
+
+    // requests without a valid link cannot make us burn bcrypt rounds, and a
+    // rejected password leaves the link usable
+    let user_token = find_user_token(&app_state, &payload.token, UserTokenPurpose::PasswordReset)
+        .await?
+        .ok_or_else(invalid_token)?;
+
+    app_state.password_policy.validate(&payload.password)?;
+    let password_hash = hash_password(&payload.password, app_state.config.bcrypt_cost)?;
+
+    if !claim_user_token(&app_state, &user_token).await? {
+        return Err(invalid_token());
+    }
+    let user_id = user_token.user_id;
+
+    Users::update_many()
+        .col_expr(Column::PasswordHash, Expr::value(password_hash))
+        .filter(Column::Id.eq(user_id))
+        .exec(&app_state.db)
+        .await?;
+
+    AuditEvent::new(AuditAction::Updated)
+        .actor(user_id)
+        .ip(client_ip)
+        .target(AuditTarget::User, user_id)
+        .changes(redacted_change("password_hash"))
+        .record(&app_state.db)
+        .await;
+
+    // whoever knew the old password must not stay logged in
+    sign_out_everywhere(&app_state, user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn send_reset_email(app_state: &AppState, email: &str) -> Result<(), AppError> {
+    let user = Users::find()
+        .filter(email_matches(email))
+        .one(&app_state.db)
+        .await?;
+
+    let Some(user) = user else {
+        return Ok(());
+    };
+
+    if app_state.email_quota.check(&user.email).is_err() {
+        tracing::warn!("email quota exceeded | user {}", user.id);
+        return Ok(());
+    }
+
+    email_reset_link(app_state, user).await
+}
+
+/// Sends a reset link without counting it against `email_quota`, for callers
+/// that leave the user without a usable password and cannot skip the email.
+pub async fn email_reset_link(app_state: &AppState, user: User) -> Result<(), AppError> {
+    let token = issue_user_token(
+        app_state,
+        user.id,
+        UserTokenPurpose::PasswordReset,
+        chrono::Duration::minutes(RESET_TOKEN_TTL_MINUTES),
+    )
+    .await?;
+
+    let reset_url = format!(
+        "{}/reset-password?token={}",
+        app_state.config.app_base_url, token
+    );
+
+    app_state
+        .mailer
+        .send(Email {
+            to: user.email,
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Hi {},\n\n\
+                Use the link below to choose a new password. It expires in {} minutes \
+                and can only be used once.\n\n{}\n\n\
+                If you did not ask for this, you can ignore this email.",
+                user.name, RESET_TOKEN_TTL_MINUTES, reset_url
+            ),
+        })
+        .await
+}
+
+async fn sign_out_everywhere(app_state: &AppState, user_id: Uuid) -> Result<(), AppError> {
+    app_state
+        .revocations
+        .revoke_all_for_user(&app_state.db, user_id)
+        .await?;
+    revoke_user_sessions(app_state, user_id).await
+}
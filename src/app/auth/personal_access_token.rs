@@ -0,0 +1,167 @@
+use std::str::FromStr;
+
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::personal_access_tokens::{
+    Column as PersonalAccessTokenColumn, Entity as PersonalAccessTokens,
+};
+use crate::app::orm::users::Entity as Users;
+
+use super::model::{AuthMethod, Permission, Role, UserInfo};
+use super::token::{generate_opaque_token, hash_token};
+
+/// Lets the auth middleware tell personal access tokens apart from JWTs.
+pub const PERSONAL_ACCESS_TOKEN_PREFIX: &str = "gwd_pat_";
+
+/// `last_used_at` is only written when it is older than this, so a busy
+/// script does not cause a write on every request.
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+pub fn generate_personal_access_token() -> String {
+    format!(
+        "{}{}",
+        PERSONAL_ACCESS_TOKEN_PREFIX,
+        generate_opaque_token()
+    )
+}
+
+pub fn parse_scopes(scopes: &str) -> Vec<Permission> {
+    scopes
+        .split_whitespace()
+        .filter_map(|scope| Permission::from_str(scope).ok())
+        .collect()
+}
+
+pub fn format_scopes(scopes: &[Permission]) -> String {
+    scopes
+        .iter()
+        .map(Permission::as_str)
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Resolves a personal access token to the user it belongs to. Returns `None`
+/// for unknown, expired or revoked tokens.
+pub async fn authenticate_personal_access_token(
+    app_state: &AppState,
+    token: &str,
+) -> Result<Option<UserInfo>, AppError> {
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+    let personal_access_token = PersonalAccessTokens::find()
+        .filter(PersonalAccessTokenColumn::TokenHash.eq(hash_token(token)))
+        .filter(PersonalAccessTokenColumn::RevokedAt.is_null())
+        .filter(PersonalAccessTokenColumn::ExpiresAt.gt(now))
+        .one(&app_state.db)
+        .await?;
+
+    let Some(personal_access_token) = personal_access_token else {
+        return Ok(None);
+    };
+
+    let Some(user) = Users::find_by_id(personal_access_token.user_id)
+        .one(&app_state.db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let Ok(role) = Role::from_str(&user.role) else {
+        return Ok(None);
+    };
+
+    let last_used_is_stale = personal_access_token
+        .last_used_at
+        .is_none_or(|last_used_at| (now - last_used_at).num_seconds() >= LAST_USED_RESOLUTION_SECS);
+    if last_used_is_stale {
+        touch_last_used(app_state, personal_access_token.id, now).await?;
+    }
+
+    Ok(Some(UserInfo {
+        id: user.id.to_string(),
+        role,
+        auth_method: AuthMethod::PersonalAccessToken,
+        scopes: Some(parse_scopes(&personal_access_token.scopes)),
+        csrf_token_hash: None,
+        session_id: None,
+    }))
+}
+
+async fn touch_last_used(
+    app_state: &AppState,
+    id: Uuid,
+    now: DateTimeWithTimeZone,
+) -> Result<(), AppError> {
+    PersonalAccessTokens::update_many()
+        .col_expr(PersonalAccessTokenColumn::LastUsedAt, Expr::value(now))
+        .filter(PersonalAccessTokenColumn::Id.eq(id))
+        .exec(&app_state.db)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_user(role: Role, scopes: &str) -> UserInfo {
+        UserInfo {
+            scopes: Some(parse_scopes(scopes)),
+            ..UserInfo::for_tests(role, AuthMethod::PersonalAccessToken)
+        }
+    }
+
+    #[test]
+    fn scopes_round_trip_and_skip_unknown_names() {
+        let scopes = parse_scopes("posts:read  media:upload posts:everything");
+        assert_eq!(scopes, [Permission::PostsRead, Permission::MediaUpload]);
+        assert_eq!(format_scopes(&scopes), "posts:read media:upload");
+        assert!(parse_scopes("").is_empty());
+    }
+
+    #[test]
+    fn tokens_are_limited_to_their_scopes() {
+        let user = token_user(Role::Admin, "posts:read");
+
+        assert!(user.has_permission(Permission::PostsRead));
+        assert!(!user.has_permission(Permission::PostsWrite));
+        assert!(!user.has_permission(Permission::UsersManage));
+    }
+
+    #[test]
+    fn scopes_never_exceed_the_role() {
+        let user = token_user(Role::Contributor, "posts:read posts:publish");
+
+        assert!(user.has_permission(Permission::PostsRead));
+        assert!(!user.has_permission(Permission::PostsPublish));
+    }
+
+    #[test]
+    fn tokens_cannot_manage_account_security() {
+        let token = token_user(Role::Admin, "users:manage");
+        assert!(token
+            .ensure_session()
+            .is_err_and(|err| err.status == axum::http::StatusCode::FORBIDDEN));
+
+        let session = UserInfo {
+            auth_method: AuthMethod::Cookie,
+            scopes: None,
+            session_id: Some(Uuid::new_v4()),
+            ..token
+        };
+        assert!(session.ensure_session().is_ok());
+        assert!(session.has_permission(Permission::UsersManage));
+    }
+
+    #[test]
+    fn generated_tokens_carry_the_prefix() {
+        let token = generate_personal_access_token();
+        assert!(token.starts_with(PERSONAL_ACCESS_TOKEN_PREFIX));
+        assert_ne!(token, generate_personal_access_token());
+    }
+}
@@ -0,0 +1,223 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::app::auth::login::controller::complete_login;
+use crate::app::auth::model::Role;
+use crate::app::auth::password::hash_password;
+use crate::app::auth::token::hash_token;
+use crate::app::auth::user_token::{consume_user_token, UserTokenPurpose};
+use crate::app::common::client_ip::ClientInfo;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::invitations::{Column as InvitationColumn, Entity as Invitations};
+use crate::app::orm::users::{Column, Entity as Users};
+
+use super::model::{
+    AcceptInvitationRequest, RegisterRequest, ResendVerificationRequest, VerifyEmailRequest,
+};
+use super::service::{
+    create_user, email_matches, email_taken, normalize_email, send_verification_email,
+    validate_name, NewUser,
+};
+
+const INVALID_INVITATION_ERROR: &str = "Invalid or expired invitation";
+
+/// Self-registration, only available when `REGISTRATION_OPEN` is set. Answers
+/// `202 Accepted` whether or not the address already has an account; the
+/// account can log in once the emailed link is followed.
+pub async fn register(
+    State(app_state): State<Arc<AppState>>,
+    payload_r: Result<Json<RegisterRequest>, JsonRejection>,
+) -> Result<StatusCode, AppError> {
+    if !app_state.config.registration_open {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "Registration is closed".to_string(),
+        ));
+    }
+
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let name = validate_name(&payload.name)?;
+    let email = normalize_email(&payload.email)?;
+    app_state.password_policy.validate(&payload.password)?;
+    // hashed before the lookup so both outcomes take the same time
+    let password_hash = hash_password(&payload.password, app_state.config.bcrypt_cost)?;
+
+    if email_taken(&app_state, &email).await? {
+        tracing::info!("registration for existing email ignored");
+        return Ok(StatusCode::ACCEPTED);
+    }
+
+    let user = create_user(
+        &app_state,
+        NewUser {
+            name,
+            email,
+            password_hash,
+            role: app_state.config.registration_role,
+            email_verified_at: None,
+        },
+    )
+    .await?;
+
+    tokio::spawn(async move {
+        if let Err(err) = send_verification_email(&app_state, &user).await {
+            tracing::error!("failed to send verification email | {}", err.message);
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+pub async fn verify_email(
+    State(app_state): State<Arc<AppState>>,
+    payload_r: Result<Json<VerifyEmailRequest>, JsonRejection>,
+) -> Result<StatusCode, AppError> {
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let user_id = consume_user_token(
+        &app_state,
+        &payload.token,
+        UserTokenPurpose::EmailVerification,
+    )
+    .await?
+    .ok_or_else(|| {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Invalid or expired verification link".to_string(),
+        )
+    })?;
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    Users::update_many()
+        .col_expr(Column::EmailVerifiedAt, Expr::value(now))
+        .filter(Column::Id.eq(user_id))
+        .filter(Column::EmailVerifiedAt.is_null())
+        .exec(&app_state.db)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Always answers `202 Accepted`, the email only goes out for unverified accounts.
+pub async fn resend_verification(
+    State(app_state): State<Arc<AppState>>,
+    payload_r: Result<Json<ResendVerificationRequest>, JsonRejection>,
+) -> Result<StatusCode, AppError> {
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let email = normalize_email(&payload.email)?;
+
+    tokio::spawn(async move {
+        let user = Users::find()
+            .filter(email_matches(&email))
+            .filter(Column::EmailVerifiedAt.is_null())
+            .one(&app_state.db)
+            .await;
+
+        let result = match user {
+            Ok(Some(user)) => send_verification_email(&app_state, &user).await,
+            Ok(None) => Ok(()),
+            Err(err) => Err(err.into()),
+        };
+
+        if let Err(err) = result {
+            tracing::error!("failed to send verification email | {}", err.message);
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Creates the invited account with the role chosen by the admin and signs
+/// the new user in.
+pub async fn accept_invitation(
+    State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
+    payload_r: Result<Json<AcceptInvitationRequest>, JsonRejection>,
+) -> Result<Response, AppError> {
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let name = validate_name(&payload.name)?;
+
+    let invalid_invitation = || {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            INVALID_INVITATION_ERROR.to_string(),
+        )
+    };
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    let invitation = Invitations::find()
+        .filter(InvitationColumn::TokenHash.eq(hash_token(&payload.token)))
+        .filter(InvitationColumn::AcceptedAt.is_null())
+        .filter(InvitationColumn::ExpiresAt.gt(now))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(invalid_invitation)?;
+
+    if email_taken(&app_state, &invitation.email).await? {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "An account with this email already exists".to_string(),
+        ));
+    }
+
+    let role = Role::from_str(&invitation.role)
+        .map_err(|err| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+
+    // only hash once the link is known to be good
+    app_state.password_policy.validate(&payload.password)?;
+    let password_hash = hash_password(&payload.password, app_state.config.bcrypt_cost)?;
+
+    // guards against the same link being redeemed twice concurrently
+    let claimed = Invitations::update_many()
+        .col_expr(InvitationColumn::AcceptedAt, Expr::value(now))
+        .filter(InvitationColumn::Id.eq(invitation.id))
+        .filter(InvitationColumn::AcceptedAt.is_null())
+        .exec(&app_state.db)
+        .await?;
+
+    if claimed.rows_affected == 0 {
+        return Err(invalid_invitation());
+    }
+
+    let user = create_user(
+        &app_state,
+        NewUser {
+            name,
+            email: invitation.email,
+            password_hash,
+            role,
+            // following the link proved the address
+            email_verified_at: Some(now),
+        },
+    )
+    .await?;
+
+    complete_login(&app_state, user, &client, false)
+        .await
+        .map(IntoResponse::into_response)
+}
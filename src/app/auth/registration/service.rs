@@ -0,0 +1,175 @@
+use axum::http::StatusCode;
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::{Expr, Func, SimpleExpr};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, QueryFilter};
+
+use crate::app::auth::model::Role;
+use crate::app::auth::user_token::{issue_user_token, UserTokenPurpose};
+use crate::app::common::audit::{diff, AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::common::mailer::Email;
+use crate::app::orm::users::{
+    ActiveModel as UserActiveModel, Column, Entity as Users, Model as User,
+};
+
+pub const EMAIL_VERIFICATION_TTL_HOURS: i64 = 24;
+const MAX_NAME_LENGTH: usize = 100;
+const MAX_EMAIL_LENGTH: usize = 254;
+
+pub struct NewUser {
+    pub name: String,
+    pub email: String,
+    pub password_hash: String,
+    pub role: Role,
+    /// Set when the address is already proven, e.g. by an invitation link.
+    pub email_verified_at: Option<DateTimeWithTimeZone>,
+}
+
+/// Emails are stored lowercased so lookups do not depend on how the address
+/// was typed.
+pub fn normalize_email(email: &str) -> Result<String, AppError> {
+    let email = email.trim().to_lowercase();
+
+    let valid = email.len() <= MAX_EMAIL_LENGTH
+        && !email.chars().any(char::is_whitespace)
+        && email
+            .split_once('@')
+            .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'));
+
+    if !valid {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "A valid email address is required".to_string(),
+        ));
+    }
+
+    Ok(email)
+}
+
+pub fn validate_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Name must be between 1 and {} characters", MAX_NAME_LENGTH),
+        ));
+    }
+
+    Ok(name.to_string())
+}
+
+/// Filter for the account with `email`, ignoring case. Accounts created
+/// before emails were normalized can still be stored in mixed case.
+pub fn email_matches(email: &str) -> SimpleExpr {
+    Expr::expr(Func::lower(Expr::col(Column::Email))).eq(email.trim().to_lowercase())
+}
+
+pub async fn email_taken(app_state: &AppState, email: &str) -> Result<bool, AppError> {
+    let user = Users::find()
+        .filter(email_matches(email))
+        .one(&app_state.db)
+        .await?;
+
+    Ok(user.is_some())
+}
+
+pub async fn create_user(app_state: &AppState, new_user: NewUser) -> Result<User, AppError> {
+    let user = UserActiveModel {
+        id: ActiveValue::Set(Uuid::new_v4()),
+        name: ActiveValue::Set(new_user.name),
+        email: ActiveValue::Set(new_user.email),
+        password_hash: ActiveValue::Set(new_user.password_hash),
+        created_at: ActiveValue::Set(chrono::Utc::now().into()),
+        role: ActiveValue::Set(new_user.role.as_str().to_string()),
+        email_verified_at: ActiveValue::Set(new_user.email_verified_at),
+        ..Default::default()
+    }
+    .insert(&app_state.db)
+    .await?;
+
+    tracing::info!("user created | {} | {}", user.id, user.role);
+
+    AuditEvent::new(AuditAction::Created)
+        .actor(user.id)
+        .target(AuditTarget::User, user.id)
+        .changes(diff(None, Some(&user)))
+        .record(&app_state.db)
+        .await;
+
+    Ok(user)
+}
+
+pub async fn send_verification_email(app_state: &AppState, user: &User) -> Result<(), AppError> {
+    if app_state.email_quota.check(&user.email).is_err() {
+        tracing::warn!("email quota exceeded | user {}", user.id);
+        return Ok(());
+    }
+
+    let token = issue_user_token(
+        app_state,
+        user.id,
+        UserTokenPurpose::EmailVerification,
+        chrono::Duration::hours(EMAIL_VERIFICATION_TTL_HOURS),
+    )
+    .await?;
+
+    let verify_url = format!(
+        "{}/verify-email?token={}",
+        app_state.config.app_base_url, token
+    );
+
+    app_state
+        .mailer
+        .send(Email {
+            to: user.email.clone(),
+            subject: "Confirm your email address".to_string(),
+            body: format!(
+                "Hi {},\n\n\
+                Please confirm your email address to finish signing up. The link \
+                expires in {} hours.\n\n{}\n\n\
+                If you did not sign up, you can ignore this email.",
+                user.name, EMAIL_VERIFICATION_TTL_HOURS, verify_url
+            ),
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{DbBackend, QueryTrait};
+
+    use super::*;
+
+    #[test]
+    fn normalizes_emails() {
+        assert_eq!(
+            normalize_email("  Jane@Example.com ").ok().as_deref(),
+            Some("jane@example.com")
+        );
+        for email in [
+            "",
+            "jane",
+            "@example.com",
+            "jane@localhost",
+            "ja ne@example.com",
+        ] {
+            assert!(normalize_email(email).is_err(), "{}", email);
+        }
+    }
+
+    #[test]
+    fn email_lookups_ignore_case() {
+        let sql = Users::find()
+            .filter(email_matches(" Jane@Example.com "))
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(
+            sql.ends_with(r#"WHERE LOWER("email") = 'jane@example.com'"#),
+            "{}",
+            sql
+        );
+    }
+}
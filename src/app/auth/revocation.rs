@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+
+use crate::app::common::errors::AppError;
+use crate::app::orm::revoked_tokens::{
+    ActiveModel as RevokedTokenActiveModel, Column as RevokedTokenColumn, Entity as RevokedTokens,
+};
+use crate::app::orm::sessions::Entity as Sessions;
+use crate::app::orm::users::{Column as UserColumn, Entity as Users};
+
+use super::model::Claims;
+
+/// How long a lookup is trusted before asking the database again. Revocations
+/// made through another instance take at most this long to be enforced here.
+const CACHE_TTL: Duration = Duration::from_secs(30);
+const MAX_CACHE_ENTRIES: usize = 10_000;
+
+/// Answers "is this access token still valid?" for the auth middleware while
+/// keeping the database out of the hot path.
+#[derive(Default)]
+pub struct RevocationCache {
+    tokens: TtlCache<Uuid, bool>,
+    users: TtlCache<Uuid, Option<UserStatus>>,
+    sessions: TtlCache<Uuid, bool>,
+}
+
+#[derive(Clone, Copy)]
+struct UserStatus {
+    tokens_valid_after: Option<DateTimeWithTimeZone>,
+}
+
+impl RevocationCache {
+    pub async fn is_revoked(
+        &self,
+        db: &DatabaseConnection,
+        claims: &Claims,
+    ) -> Result<bool, AppError> {
+        let (Ok(user_id), Ok(jti)) = (Uuid::from_str(&claims.sub), Uuid::from_str(&claims.jti))
+        else {
+            return Ok(true);
+        };
+
+        let user_status = match self.users.get(&user_id) {
+            Some(status) => status,
+            None => {
+                let status = Users::find_by_id(user_id)
+                    .one(db)
+                    .await?
+                    .map(|user| UserStatus {
+                        tokens_valid_after: user.tokens_valid_after,
+                    });
+                self.users.insert(user_id, status);
+                status
+            }
+        };
+
+        let Some(user_status) = user_status else {
+            // the user was deleted
+            return Ok(true);
+        };
+
+        if let Some(tokens_valid_after) = user_status.tokens_valid_after {
+            if (claims.iat as i64) < tokens_valid_after.timestamp() {
+                return Ok(true);
+            }
+        }
+
+        let revoked = match self.tokens.get(&jti) {
+            Some(revoked) => revoked,
+            None => {
+                let revoked = RevokedTokens::find_by_id(jti).one(db).await?.is_some();
+                self.tokens.insert(jti, revoked);
+                revoked
+            }
+        };
+
+        if revoked {
+            return Ok(true);
+        }
+
+        // access tokens die with the session they were issued for
+        let Ok(session_id) = Uuid::from_str(&claims.sid) else {
+            return Ok(true);
+        };
+
+        let session_revoked = match self.sessions.get(&session_id) {
+            Some(revoked) => revoked,
+            None => {
+                let revoked =
+                    Sessions::find_by_id(session_id)
+                        .one(db)
+                        .await?
+                        .is_none_or(|session| {
+                            session.revoked_at.is_some() || session.expires_at < chrono::Utc::now()
+                        });
+                self.sessions.insert(session_id, revoked);
+                revoked
+            }
+        };
+
+        Ok(session_revoked)
+    }
+
+    /// Drops the cached state of a session so its revocation takes effect
+    /// immediately on this instance.
+    pub fn forget_session(&self, session_id: Uuid) {
+        self.sessions.remove(&session_id);
+    }
+
+    pub async fn revoke_token(
+        &self,
+        db: &DatabaseConnection,
+        claims: &Claims,
+    ) -> Result<(), AppError> {
+        let (Ok(user_id), Ok(jti)) = (Uuid::from_str(&claims.sub), Uuid::from_str(&claims.jti))
+        else {
+            return Ok(());
+        };
+
+        let expires_at =
+            chrono::DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(chrono::Utc::now);
+
+        RevokedTokens::insert(RevokedTokenActiveModel {
+            jti: ActiveValue::Set(jti),
+            user_id: ActiveValue::Set(user_id),
+            expires_at: ActiveValue::Set(expires_at.into()),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::column(RevokedTokenColumn::Jti)
+                .do_nothing()
+                .to_owned(),
+        )
+        .do_nothing()
+        .exec(db)
+        .await?;
+
+        self.tokens.insert(jti, true);
+
+        // expired tokens are rejected anyway, no need to remember them
+        RevokedTokens::delete_many()
+            .filter(RevokedTokenColumn::ExpiresAt.lt(chrono::Utc::now()))
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Invalidates every access token issued to the user so far.
+    pub async fn revoke_all_for_user(
+        &self,
+        db: &DatabaseConnection,
+        user_id: Uuid,
+    ) -> Result<(), AppError> {
+        let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+        Users::update_many()
+            .col_expr(UserColumn::TokensValidAfter, Expr::value(now))
+            .filter(UserColumn::Id.eq(user_id))
+            .exec(db)
+            .await?;
+
+        self.users.remove(&user_id);
+
+        Ok(())
+    }
+}
+
+struct CachedEntry<V> {
+    value: V,
+    cached_at: Instant,
+}
+
+struct TtlCache<K, V> {
+    entries: RwLock<HashMap<K, CachedEntry<V>>>,
+}
+
+impl<K, V> Default for TtlCache<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(key)
+            .filter(|entry| entry.cached_at.elapsed() < CACHE_TTL)
+            .map(|entry| entry.value.clone())
+    }
+
+    fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.write().unwrap();
+        if entries.len() >= MAX_CACHE_ENTRIES {
+            entries.retain(|_, entry| entry.cached_at.elapsed() < CACHE_TTL);
+            if entries.len() >= MAX_CACHE_ENTRIES {
+                entries.clear();
+            }
+        }
+        entries.insert(
+            key,
+            CachedEntry {
+                value,
+                cached_at: Instant::now(),
+            },
+        );
+    }
+
+    fn remove(&self, key: &K) {
+        self.entries.write().unwrap().remove(key);
+    }
+}
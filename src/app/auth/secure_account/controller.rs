@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use sea_orm::EntityTrait;
+
+use crate::app::auth::password_reset::controller::email_reset_link;
+use crate::app::auth::user_token::{consume_user_token, UserTokenPurpose};
+use crate::app::common::audit::{redacted_change, AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::ClientIp;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::users::Entity as Users;
+
+use super::model::SecureAccountRequest;
+use super::service::lock_down_account;
+
+/// Redeems the link from a security alert: signs the account out everywhere,
+/// discards the password and emails a password reset link.
+pub async fn secure_account(
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    payload_r: Result<Json<SecureAccountRequest>, JsonRejection>,
+) -> Result<StatusCode, AppError> {
+    let Json(payload) = payload_r.map_err(|_| AppError {
+        message: "Invalid payload".to_string(),
+        status: StatusCode::BAD_REQUEST,
+    })?;
+
+    let user_id = consume_user_token(&app_state, &payload.token, UserTokenPurpose::SecureAccount)
+        .await?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid or expired link".to_string(),
+            )
+        })?;
+
+    let user = Users::find_by_id(user_id)
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "User not found".to_string()))?;
+
+    lock_down_account(&app_state, user.id).await?;
+
+    tracing::warn!("account secured by owner | {}", user.id);
+
+    AuditEvent::new(AuditAction::TokenRevoked)
+        .actor(user.id)
+        .ip(client_ip)
+        .target(AuditTarget::User, user.id)
+        .record(&app_state.db)
+        .await;
+    AuditEvent::new(AuditAction::Updated)
+        .actor(user.id)
+        .ip(client_ip)
+        .target(AuditTarget::User, user.id)
+        .changes(redacted_change("password_hash"))
+        .record(&app_state.db)
+        .await;
+
+    // the password is gone, so this email must not be dropped by the quota
+    tokio::spawn(async move {
+        if let Err(err) = email_reset_link(&app_state, user).await {
+            tracing::error!("failed to send password reset email | {}", err.message);
+        }
+    });
+
+    Ok(StatusCode::NO_CONTENT)
+}
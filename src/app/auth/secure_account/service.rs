@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::app::auth::mfa::service::delete_recovery_codes;
+use crate::app::auth::password::hash_password;
+use crate::app::auth::session::service::revoke_user_sessions;
+use crate::app::auth::token::generate_opaque_token;
+use crate::app::auth::user_token::{issue_user_token, UserTokenPurpose};
+use crate::app::common::client_ip::ClientInfo;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::common::mailer::Email;
+use crate::app::orm::personal_access_tokens::{
+    Column as PersonalAccessTokenColumn, Entity as PersonalAccessTokens,
+};
+use crate::app::orm::sessions::{Column as SessionColumn, Entity as Sessions};
+use crate::app::orm::users::{Column as UserColumn, Entity as Users, Model as User};
+use crate::app::orm::webauthn_credentials::{Column as CredentialColumn, Entity as Credentials};
+
+/// The link in an alert has to outlive the time it takes to notice the email.
+const SECURE_ACCOUNT_TOKEN_TTL_HOURS: i64 = 72;
+
+pub enum SecurityAlert {
+    NewDevice(ClientInfo),
+    Lockout { failures: u32 },
+}
+
+/// Alerts the user when a login comes from an IP address or user agent none
+/// of their earlier sessions used. Has to run before the new session is
+/// created.
+pub async fn alert_on_new_device(
+    app_state: &Arc<AppState>,
+    user: &User,
+    client: &ClientInfo,
+) -> Result<(), AppError> {
+    if is_new_device(app_state, user.id, client).await? {
+        tracing::info!("login from a new device | {}", user.id);
+        send_security_alert(app_state, user, SecurityAlert::NewDevice(client.clone()));
+    }
+
+    Ok(())
+}
+
+/// Users without recorded sessions have nothing to compare against and are
+/// never reported.
+async fn is_new_device(
+    app_state: &AppState,
+    user_id: Uuid,
+    client: &ClientInfo,
+) -> Result<bool, AppError> {
+    let has_history = Sessions::find()
+        .filter(SessionColumn::UserId.eq(user_id))
+        .filter(SessionColumn::IpAddress.is_not_null())
+        .one(&app_state.db)
+        .await?
+        .is_some();
+
+    if !has_history {
+        return Ok(false);
+    }
+
+    let known_ip = Sessions::find()
+        .filter(SessionColumn::UserId.eq(user_id))
+        .filter(SessionColumn::IpAddress.eq(client.ip.to_string()))
+        .one(&app_state.db)
+        .await?
+        .is_some();
+
+    let known_user_agent = match &client.user_agent {
+        Some(user_agent) => Sessions::find()
+            .filter(SessionColumn::UserId.eq(user_id))
+            .filter(SessionColumn::UserAgent.eq(user_agent))
+            .one(&app_state.db)
+            .await?
+            .is_some(),
+        None => true,
+    };
+
+    Ok(!known_ip || !known_user_agent)
+}
+
+/// Emails the alert in the background, failures are only logged.
+pub fn send_security_alert(app_state: &Arc<AppState>, user: &User, alert: SecurityAlert) {
+    let app_state = app_state.clone();
+    let user = user.clone();
+
+    tokio::spawn(async move {
+        if let Err(err) = deliver_security_alert(&app_state, &user, alert).await {
+            tracing::error!("failed to send security alert | {}", err.message);
+        }
+    });
+}
+
+async fn deliver_security_alert(
+    app_state: &AppState,
+    user: &User,
+    alert: SecurityAlert,
+) -> Result<(), AppError> {
+    if app_state.email_quota.check(&user.email).is_err() {
+        tracing::warn!("email quota exceeded | user {}", user.id);
+        return Ok(());
+    }
+
+    let token = issue_user_token(
+        app_state,
+        user.id,
+        UserTokenPurpose::SecureAccount,
+        chrono::Duration::hours(SECURE_ACCOUNT_TOKEN_TTL_HOURS),
+    )
+    .await?;
+
+    let secure_url = format!(
+        "{}/secure-account?token={}",
+        app_state.config.app_base_url, token
+    );
+
+    let (subject, what_happened) = match alert {
+        SecurityAlert::NewDevice(client) => (
+            "New sign-in to your account",
+            format!(
+                "Your account was just signed in to from a new device or location.\n\n\
+                IP address: {}\nDevice: {}",
+                client.ip,
+                client.user_agent.as_deref().unwrap_or("unknown")
+            ),
+        ),
+        SecurityAlert::Lockout { failures } => (
+            "Sign-ins to your account were blocked",
+            format!(
+                "After {} failed attempts to sign in to your account, further \
+                attempts are blocked for a while.",
+                failures
+            ),
+        ),
+    };
+
+    app_state
+        .mailer
+        .send(Email {
+            to: user.email.clone(),
+            subject: subject.to_string(),
+            body: format!(
+                "Hi {},\n\n{}\n\n\
+                If this was you, there is nothing to do. Otherwise use the link below \
+                to sign out every session and token and choose a new password. It \
+                expires in {} hours.\n\n{}",
+                user.name, what_happened, SECURE_ACCOUNT_TOKEN_TTL_HOURS, secure_url
+            ),
+        })
+        .await
+}
+
+/// Locks whoever else may be using the account out: every session, access
+/// token and personal access token is revoked, passkeys and two-factor
+/// authentication are removed, magic link sign in is turned off and the
+/// password is replaced by a random one, so the owner has to go through a
+/// password reset.
+pub async fn lock_down_account(app_state: &AppState, user_id: Uuid) -> Result<(), AppError> {
+    let password_hash = hash_password(&generate_opaque_token(), app_state.config.bcrypt_cost)?;
+
+    Users::update_many()
+        .col_expr(UserColumn::PasswordHash, Expr::value(password_hash))
+        .col_expr(UserColumn::PasswordlessEnabled, Expr::value(false))
+        // a TOTP secret enrolled by the intruder would lock the owner out
+        .col_expr(UserColumn::TotpSecret, Expr::value(Option::<String>::None))
+        .col_expr(
+            UserColumn::TotpEnabledAt,
+            Expr::value(Option::<DateTimeWithTimeZone>::None),
+        )
+        .col_expr(
+            UserColumn::TotpLastUsedStep,
+            Expr::value(Option::<i64>::None),
+        )
+        .filter(UserColumn::Id.eq(user_id))
+        .exec(&app_state.db)
+        .await?;
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    PersonalAccessTokens::update_many()
+        .col_expr(PersonalAccessTokenColumn::RevokedAt, Expr::value(now))
+        .filter(PersonalAccessTokenColumn::UserId.eq(user_id))
+        .filter(PersonalAccessTokenColumn::RevokedAt.is_null())
+        .exec(&app_state.db)
+        .await?;
+
+    // a passkey registered by the intruder would outlive the password reset
+    Credentials::delete_many()
+        .filter(CredentialColumn::UserId.eq(user_id))
+        .exec(&app_state.db)
+        .await?;
+    delete_recovery_codes(app_state, user_id).await?;
+
+    app_state
+        .revocations
+        .revoke_all_for_user(&app_state.db, user_id)
+        .await?;
+    revoke_user_sessions(app_state, user_id).await
+}
@@ -0,0 +1,55 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::app::common::core::AppState;
+use crate::app::orm::sessions::{Column as SessionColumn, Entity as Sessions};
+
+/// How often the sessions seen since the last flush get their
+/// `last_active_at` bumped. Also the precision of the reported activity.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Collects the sessions that made authenticated requests so their last
+/// activity can be written in one statement instead of once per request.
+#[derive(Default)]
+pub struct SessionActivity {
+    pending: Mutex<HashSet<Uuid>>,
+}
+
+impl SessionActivity {
+    pub fn touch(&self, session_id: Uuid) {
+        self.pending.lock().unwrap().insert(session_id);
+    }
+
+    fn take(&self) -> Vec<Uuid> {
+        self.pending.lock().unwrap().drain().collect()
+    }
+}
+
+/// Writes the pending activity every [`FLUSH_INTERVAL`], for as long as the
+/// server runs.
+pub async fn flush_session_activity(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let session_ids = app_state.session_activity.take();
+        if session_ids.is_empty() {
+            continue;
+        }
+
+        let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+        if let Err(err) = Sessions::update_many()
+            .col_expr(SessionColumn::LastActiveAt, Expr::value(now))
+            .filter(SessionColumn::Id.is_in(session_ids))
+            .exec(&app_state.db)
+            .await
+        {
+            tracing::error!("failed to record session activity | {}", err);
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+
+use crate::app::auth::cookies::REFRESH_TOKEN_COOKIE_NAME;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+
+use super::service::{rotate_refresh_token, session_cookie_headers};
+
+pub async fn refresh(
+    State(app_state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AppError> {
+    let refresh_token = jar.get(REFRESH_TOKEN_COOKIE_NAME).ok_or_else(|| {
+        AppError::new(
+            StatusCode::UNAUTHORIZED,
+            "Missing refresh token".to_string(),
+        )
+    })?;
+
+    let rotated = rotate_refresh_token(&app_state, refresh_token.value()).await?;
+    let headers = session_cookie_headers(&app_state, &rotated.user, rotated.issued)?;
+
+    Ok((headers, ()))
+}
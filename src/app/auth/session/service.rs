@@ -0,0 +1,225 @@
+use axum::http::header::SET_COOKIE;
+use axum::http::{HeaderName, StatusCode};
+use axum::response::AppendHeaders;
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::app::auth::cookies::{access_token_cookie, csrf_token_cookie, refresh_token_cookie};
+use crate::app::auth::token::{generate_access_token, generate_opaque_token, hash_token};
+use crate::app::common::client_ip::ClientInfo;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::session_refresh_tokens::{
+    ActiveModel as RefreshTokenActiveModel, Column as RefreshTokenColumn, Entity as RefreshTokens,
+};
+use crate::app::orm::sessions::{
+    ActiveModel as SessionActiveModel, Column as SessionColumn, Entity as Sessions,
+    Model as Session,
+};
+use crate::app::orm::users::{Entity as Users, Model as User};
+
+const INVALID_SESSION_ERROR: &str = "Invalid or expired session";
+
+pub type SessionCookieHeaders = AppendHeaders<Vec<(HeaderName, String)>>;
+
+pub struct IssuedSession {
+    pub session: Session,
+    pub refresh_token: String,
+    /// Only known when the session is created, the cookie then lives as
+    /// long as the session.
+    pub csrf_token: Option<String>,
+}
+
+pub struct RotatedSession {
+    pub user: User,
+    pub issued: IssuedSession,
+}
+
+/// Starts a session. With `remember_me` it lasts for the configured
+/// "remember me" duration and its cookies survive closing the browser.
+pub async fn create_session(
+    app_state: &AppState,
+    user_id: Uuid,
+    client: &ClientInfo,
+    remember_me: bool,
+) -> Result<IssuedSession, AppError> {
+    let now = chrono::Utc::now();
+    let ttl = if remember_me {
+        app_state.config.session.remember_me_ttl
+    } else {
+        app_state.config.session.session_ttl
+    };
+    let expires_at = now + ttl;
+    let csrf_token = generate_opaque_token();
+
+    let session = SessionActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        expires_at: ActiveValue::Set(expires_at.into()),
+        csrf_token_hash: ActiveValue::Set(Some(hash_token(&csrf_token))),
+        user_agent: ActiveValue::Set(client.user_agent.clone()),
+        ip_address: ActiveValue::Set(Some(client.ip.to_string())),
+        last_active_at: ActiveValue::Set(Some(now.into())),
+        remember_me: ActiveValue::Set(remember_me),
+        ..Default::default()
+    }
+    .insert(&app_state.db)
+    .await?;
+
+    let refresh_token = insert_refresh_token(app_state, &session).await?;
+
+    Ok(IssuedSession {
+        session,
+        refresh_token,
+        csrf_token: Some(csrf_token),
+    })
+}
+
+/// Exchanges a refresh token for a new one belonging to the same session.
+///
+/// Every refresh token can be used exactly once. Presenting a token that was
+/// already rotated means it has leaked, so the whole session is revoked.
+pub async fn rotate_refresh_token(
+    app_state: &AppState,
+    refresh_token: &str,
+) -> Result<RotatedSession, AppError> {
+    let stored_token = RefreshTokens::find()
+        .filter(RefreshTokenColumn::TokenHash.eq(hash_token(refresh_token)))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(invalid_session_error)?;
+
+    let session = Sessions::find_by_id(stored_token.session_id)
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(invalid_session_error)?;
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    if session.revoked_at.is_some() || session.expires_at <= now {
+        return Err(invalid_session_error());
+    }
+
+    let claimed = RefreshTokens::update_many()
+        .col_expr(RefreshTokenColumn::UsedAt, Expr::value(now))
+        .filter(RefreshTokenColumn::Id.eq(stored_token.id))
+        .filter(RefreshTokenColumn::UsedAt.is_null())
+        .exec(&app_state.db)
+        .await?;
+
+    if claimed.rows_affected == 0 {
+        tracing::warn!(
+            "refresh token reuse detected | session {} | user {}",
+            session.id,
+            session.user_id
+        );
+        revoke_session(app_state, session.id).await?;
+        return Err(invalid_session_error());
+    }
+
+    let user = Users::find_by_id(session.user_id)
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(invalid_session_error)?;
+
+    let refresh_token = insert_refresh_token(app_state, &session).await?;
+
+    Ok(RotatedSession {
+        user,
+        issued: IssuedSession {
+            session,
+            refresh_token,
+            csrf_token: None,
+        },
+    })
+}
+
+pub async fn revoke_session(app_state: &AppState, session_id: Uuid) -> Result<(), AppError> {
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+    Sessions::update_many()
+        .col_expr(SessionColumn::RevokedAt, Expr::value(now))
+        .filter(SessionColumn::Id.eq(session_id))
+        .filter(SessionColumn::RevokedAt.is_null())
+        .exec(&app_state.db)
+        .await?;
+
+    app_state.revocations.forget_session(session_id);
+
+    Ok(())
+}
+
+pub async fn revoke_user_sessions(app_state: &AppState, user_id: Uuid) -> Result<(), AppError> {
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+    let revoked = Sessions::update_many()
+        .col_expr(SessionColumn::RevokedAt, Expr::value(now))
+        .filter(SessionColumn::UserId.eq(user_id))
+        .filter(SessionColumn::RevokedAt.is_null())
+        .exec_with_returning(&app_state.db)
+        .await?;
+
+    for session in revoked {
+        app_state.revocations.forget_session(session.id);
+    }
+
+    Ok(())
+}
+
+/// Builds the `Set-Cookie` headers carrying a fresh access token and the
+/// refresh token of the given session.
+pub fn session_cookie_headers(
+    app_state: &AppState,
+    user: &User,
+    issued: IssuedSession,
+) -> Result<SessionCookieHeaders, AppError> {
+    let config = &app_state.config.session;
+    let access_token = generate_access_token(user, &issued.session, app_state)?;
+
+    let expires_at = if issued.session.remember_me {
+        let expires_at =
+            time::OffsetDateTime::from_unix_timestamp(issued.session.expires_at.timestamp())
+                .map_err(|_| AppError {
+                    message: "Failed to generate token".to_string(),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                })?;
+        Some(expires_at)
+    } else {
+        None
+    };
+
+    let mut headers = vec![
+        (
+            SET_COOKIE,
+            access_token_cookie(config, access_token).to_string(),
+        ),
+        (
+            SET_COOKIE,
+            refresh_token_cookie(config, issued.refresh_token, expires_at).to_string(),
+        ),
+    ];
+
+    if let Some(csrf_token) = issued.csrf_token {
+        let cookie = csrf_token_cookie(config, csrf_token, expires_at);
+        headers.push((SET_COOKIE, cookie.to_string()));
+    }
+
+    Ok(AppendHeaders(headers))
+}
+
+async fn insert_refresh_token(app_state: &AppState, session: &Session) -> Result<String, AppError> {
+    let refresh_token = generate_opaque_token();
+
+    RefreshTokenActiveModel {
+        session_id: ActiveValue::Set(session.id),
+        token_hash: ActiveValue::Set(hash_token(&refresh_token)),
+        ..Default::default()
+    }
+    .insert(&app_state.db)
+    .await?;
+
+    Ok(refresh_token)
+}
+
+fn invalid_session_error() -> AppError {
+    AppError::new(StatusCode::UNAUTHORIZED, INVALID_SESSION_ERROR.to_string())
+}
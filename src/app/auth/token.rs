@@ -0,0 +1,166 @@
+use std::str::FromStr;
+
+use axum::http::StatusCode;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::Algorithm;
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::sessions::Model as Session;
+use crate::app::orm::users::Model as User;
+
+use super::model::{Claims, OidcFlowClaims, Role, ScopedClaims};
+
+/// Audience of the token handed out between the password and the second factor.
+pub const MFA_PENDING_AUDIENCE: &str = "mfa-pending";
+
+/// Audience of the token embedded in a magic login link.
+pub const MAGIC_LINK_AUDIENCE: &str = "magic-link";
+
+/// Audience of the cookie holding the state of an OpenID Connect login.
+pub const OIDC_FLOW_AUDIENCE: &str = "oidc-flow";
+
+pub fn generate_access_token(
+    user: &User,
+    session: &Session,
+    app_state: &AppState,
+) -> Result<String, AppError> {
+    let role = Role::from_str(&user.role)
+        .map_err(|err| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+    let now = chrono::Utc::now();
+    let expire_time = now + app_state.config.session.access_token_ttl;
+
+    let claims = Claims {
+        sub: user.id.to_string(),
+        sid: session.id.to_string(),
+        jti: Uuid::new_v4().to_string(),
+        role,
+        csrf: session.csrf_token_hash.clone(),
+        exp: expire_time.timestamp().unsigned_abs() as usize,
+        iat: now.timestamp().unsigned_abs() as usize,
+    };
+
+    encode_claims(&claims, app_state)
+}
+
+pub fn decode_access_token(token: &str, app_state: &AppState) -> Option<Claims> {
+    decode_claims(
+        token,
+        app_state,
+        jsonwebtoken::Validation::new(Algorithm::EdDSA),
+    )
+}
+
+/// Decodes an access token whose signature is valid but which may already be
+/// expired. Only meant for revoking it, never for authenticating a request.
+pub fn decode_expired_access_token(token: &str, app_state: &AppState) -> Option<Claims> {
+    let mut validation = jsonwebtoken::Validation::new(Algorithm::EdDSA);
+    validation.validate_exp = false;
+    decode_claims(token, app_state, validation)
+}
+
+/// Generates a short-lived token that is only accepted where `audience` is
+/// expected. The `aud` claim also keeps it from passing as an access token.
+pub fn generate_scoped_token(
+    user_id: &Uuid,
+    audience: &str,
+    remember_me: bool,
+    ttl: chrono::Duration,
+    app_state: &AppState,
+) -> Result<String, AppError> {
+    let now = chrono::Utc::now();
+
+    let claims = ScopedClaims {
+        sub: user_id.to_string(),
+        aud: audience.to_string(),
+        jti: Uuid::new_v4().to_string(),
+        remember_me,
+        exp: (now + ttl).timestamp().unsigned_abs() as usize,
+        iat: now.timestamp().unsigned_abs() as usize,
+    };
+
+    encode_claims(&claims, app_state)
+}
+
+pub fn decode_scoped_token(
+    token: &str,
+    audience: &str,
+    app_state: &AppState,
+) -> Option<ScopedClaims> {
+    let mut validation = jsonwebtoken::Validation::new(Algorithm::EdDSA);
+    validation.set_audience(&[audience]);
+    decode_claims(token, app_state, validation)
+}
+
+pub fn generate_oidc_flow_token(
+    state: String,
+    nonce: String,
+    code_verifier: String,
+    remember_me: bool,
+    ttl: chrono::Duration,
+    app_state: &AppState,
+) -> Result<String, AppError> {
+    let now = chrono::Utc::now();
+
+    let claims = OidcFlowClaims {
+        aud: OIDC_FLOW_AUDIENCE.to_string(),
+        state,
+        nonce,
+        code_verifier,
+        remember_me,
+        exp: (now + ttl).timestamp().unsigned_abs() as usize,
+        iat: now.timestamp().unsigned_abs() as usize,
+    };
+
+    encode_claims(&claims, app_state)
+}
+
+pub fn decode_oidc_flow_token(token: &str, app_state: &AppState) -> Option<OidcFlowClaims> {
+    let mut validation = jsonwebtoken::Validation::new(Algorithm::EdDSA);
+    validation.set_audience(&[OIDC_FLOW_AUDIENCE]);
+    decode_claims(token, app_state, validation)
+}
+
+fn encode_claims<T: Serialize>(claims: &T, app_state: &AppState) -> Result<String, AppError> {
+    let (kid, key) = app_state.keys.signing_key();
+    let mut header = jsonwebtoken::Header::new(Algorithm::EdDSA);
+    header.kid = Some(kid.to_string());
+
+    let token = jsonwebtoken::encode(&header, claims, key);
+
+    token.map_err(|_| AppError {
+        message: "Failed to generate token".to_string(),
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+    })
+}
+
+fn decode_claims<T: DeserializeOwned>(
+    token: &str,
+    app_state: &AppState,
+    validation: jsonwebtoken::Validation,
+) -> Option<T> {
+    let kid = jsonwebtoken::decode_header(token).ok()?.kid?;
+    let key = app_state.keys.decoding_key(&kid)?;
+
+    jsonwebtoken::decode::<T>(token, key, &validation)
+        .map(|token_data| token_data.claims)
+        .ok()
+}
+
+/// Generates a random, URL safe token for values that are stored server side
+/// (refresh tokens, reset links, ...). Only the hash of it should be persisted.
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
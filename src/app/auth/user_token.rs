@@ -0,0 +1,136 @@
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::user_tokens::{
+    ActiveModel as UserTokenActiveModel, Column as UserTokenColumn, Entity as UserTokens,
+    Model as UserToken,
+};
+
+use super::token::{generate_opaque_token, hash_token};
+
+/// What a single-use token sent to a user may be redeemed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserTokenPurpose {
+    PasswordReset,
+    MagicLink,
+    EmailVerification,
+    /// Sent with security alerts, signs the account out everywhere.
+    SecureAccount,
+}
+
+impl UserTokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserTokenPurpose::PasswordReset => "password_reset",
+            UserTokenPurpose::MagicLink => "magic_link",
+            UserTokenPurpose::EmailVerification => "email_verification",
+            UserTokenPurpose::SecureAccount => "secure_account",
+        }
+    }
+}
+
+/// Issues a new single-use token and invalidates the user's earlier unused
+/// tokens for the same purpose. Only the hash is stored.
+pub async fn issue_user_token(
+    app_state: &AppState,
+    user_id: Uuid,
+    purpose: UserTokenPurpose,
+    ttl: chrono::Duration,
+) -> Result<String, AppError> {
+    let token = generate_opaque_token();
+    store_user_token(app_state, user_id, purpose, &token, ttl).await?;
+
+    Ok(token)
+}
+
+/// Registers a token generated elsewhere (e.g. a signed JWT) so that it can
+/// only be consumed once, with the same invalidation as `issue_user_token`.
+pub async fn store_user_token(
+    app_state: &AppState,
+    user_id: Uuid,
+    purpose: UserTokenPurpose,
+    token: &str,
+    ttl: chrono::Duration,
+) -> Result<(), AppError> {
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+    UserTokens::update_many()
+        .col_expr(UserTokenColumn::UsedAt, Expr::value(now))
+        .filter(UserTokenColumn::UserId.eq(user_id))
+        .filter(UserTokenColumn::Purpose.eq(purpose.as_str()))
+        .filter(UserTokenColumn::UsedAt.is_null())
+        .exec(&app_state.db)
+        .await?;
+
+    UserTokenActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        purpose: ActiveValue::Set(purpose.as_str().to_string()),
+        token_hash: ActiveValue::Set(hash_token(token)),
+        expires_at: ActiveValue::Set(now + ttl),
+        ..Default::default()
+    }
+    .insert(&app_state.db)
+    .await?;
+
+    Ok(())
+}
+
+/// Marks the token as used and returns the user it was issued to. Returns
+/// `None` for unknown, expired or already used tokens.
+pub async fn consume_user_token(
+    app_state: &AppState,
+    token: &str,
+    purpose: UserTokenPurpose,
+) -> Result<Option<Uuid>, AppError> {
+    let Some(user_token) = find_user_token(app_state, token, purpose).await? else {
+        return Ok(None);
+    };
+
+    if !claim_user_token(app_state, &user_token).await? {
+        return Ok(None);
+    }
+
+    Ok(Some(user_token.user_id))
+}
+
+/// Looks up a token that can still be redeemed without using it up, for
+/// handlers that validate the rest of the request before
+/// [`claim_user_token`].
+pub async fn find_user_token(
+    app_state: &AppState,
+    token: &str,
+    purpose: UserTokenPurpose,
+) -> Result<Option<UserToken>, AppError> {
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+    UserTokens::find()
+        .filter(UserTokenColumn::TokenHash.eq(hash_token(token)))
+        .filter(UserTokenColumn::Purpose.eq(purpose.as_str()))
+        .filter(UserTokenColumn::UsedAt.is_null())
+        .filter(UserTokenColumn::ExpiresAt.gt(now))
+        .one(&app_state.db)
+        .await
+        .map_err(|err| err.into())
+}
+
+/// Marks a token found by [`find_user_token`] as used. Returns `false` when
+/// another request redeemed it in the meantime.
+pub async fn claim_user_token(
+    app_state: &AppState,
+    user_token: &UserToken,
+) -> Result<bool, AppError> {
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+    // guards against two requests redeeming the same token concurrently
+    let claimed = UserTokens::update_many()
+        .col_expr(UserTokenColumn::UsedAt, Expr::value(now))
+        .filter(UserTokenColumn::Id.eq(user_token.id))
+        .filter(UserTokenColumn::UsedAt.is_null())
+        .exec(&app_state.db)
+        .await?;
+
+    Ok(claimed.rows_affected > 0)
+}
@@ -0,0 +1,282 @@
+use std::net::IpAddr;
+
+use sea_orm::prelude::Uuid;
+use sea_orm::{ActiveValue, ConnectionTrait, EntityTrait};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::app::orm::audit_events::{ActiveModel as AuditEventActiveModel, Entity as AuditEvents};
+
+/// Fields whose values never end up in the audit log. A change to one is
+/// still recorded, just without the old and new value.
+const REDACTED_FIELDS: &[&str] = &[
+    "password",
+    "password_hash",
+    "token",
+    "token_hash",
+    "totp_secret",
+    "csrf_token_hash",
+];
+const REDACTED_VALUE: &str = "[redacted]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    LoginSucceeded,
+    LoginFailed,
+    TokenIssued,
+    TokenRevoked,
+    SessionRevoked,
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::LoginSucceeded => "login.succeeded",
+            AuditAction::LoginFailed => "login.failed",
+            AuditAction::TokenIssued => "token.issued",
+            AuditAction::TokenRevoked => "token.revoked",
+            AuditAction::SessionRevoked => "session.revoked",
+            AuditAction::Created => "created",
+            AuditAction::Updated => "updated",
+            AuditAction::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditTarget {
+    User,
+    /// Login attempts for an address that may not belong to any account.
+    Email,
+    Post,
+    Tag,
+    Media,
+    PersonalAccessToken,
+    Session,
+    Invitation,
+    Passkey,
+}
+
+impl AuditTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditTarget::User => "user",
+            AuditTarget::Email => "email",
+            AuditTarget::Post => "post",
+            AuditTarget::Tag => "tag",
+            AuditTarget::Media => "media",
+            AuditTarget::PersonalAccessToken => "personal_access_token",
+            AuditTarget::Session => "session",
+            AuditTarget::Invitation => "invitation",
+            AuditTarget::Passkey => "passkey",
+        }
+    }
+}
+
+/// One entry of the audit log, written with [`AuditEvent::record`].
+pub struct AuditEvent {
+    action: AuditAction,
+    actor_id: Option<Uuid>,
+    ip: Option<IpAddr>,
+    target: Option<(AuditTarget, String)>,
+    changes: Option<Value>,
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction) -> Self {
+        AuditEvent {
+            action,
+            actor_id: None,
+            ip: None,
+            target: None,
+            changes: None,
+        }
+    }
+
+    pub fn actor(mut self, actor_id: Uuid) -> Self {
+        self.actor_id = Some(actor_id);
+        self
+    }
+
+    pub fn ip(mut self, ip: IpAddr) -> Self {
+        self.ip = Some(ip);
+        self
+    }
+
+    pub fn target(mut self, target: AuditTarget, id: impl ToString) -> Self {
+        self.target = Some((target, id.to_string()));
+        self
+    }
+
+    pub fn changes(mut self, changes: Value) -> Self {
+        self.changes = Some(changes);
+        self
+    }
+
+    /// Appends the event. Failing to write it is logged rather than returned,
+    /// the action it describes has already happened.
+    pub async fn record(self, db: &impl ConnectionTrait) {
+        let action = self.action.as_str();
+        let (target_type, target_id) = match self.target {
+            Some((target, id)) => (Some(target.as_str().to_string()), Some(id)),
+            None => (None, None),
+        };
+
+        let result = AuditEvents::insert(AuditEventActiveModel {
+            action: ActiveValue::Set(action.to_string()),
+            actor_id: ActiveValue::Set(self.actor_id),
+            ip_address: ActiveValue::Set(self.ip.map(|ip| ip.to_string())),
+            target_type: ActiveValue::Set(target_type),
+            target_id: ActiveValue::Set(target_id),
+            changes: ActiveValue::Set(self.changes),
+            ..Default::default()
+        })
+        .exec(db)
+        .await;
+
+        if let Err(err) = result {
+            tracing::error!("failed to record audit event | {} | {}", action, err);
+        }
+    }
+}
+
+/// Field by field difference between two versions of a record, as
+/// `{"field": {"from": old, "to": new}}` for every top level field that
+/// changed. Pass `None` as `before` for a creation and as `after` for a
+/// deletion.
+pub fn diff<T: Serialize>(before: Option<&T>, after: Option<&T>) -> Value {
+    let before = to_object(before);
+    let after = to_object(after);
+
+    let mut changes = Map::new();
+    let fields = before
+        .keys()
+        .chain(after.keys().filter(|key| !before.contains_key(*key)));
+    for field in fields {
+        let old = before.get(field).unwrap_or(&Value::Null);
+        let new = after.get(field).unwrap_or(&Value::Null);
+        if old == new {
+            continue;
+        }
+
+        let change = if REDACTED_FIELDS.contains(&field.as_str()) {
+            json!({ "from": REDACTED_VALUE, "to": REDACTED_VALUE })
+        } else {
+            json!({ "from": old, "to": new })
+        };
+        changes.insert(field.clone(), change);
+    }
+
+    Value::Object(changes)
+}
+
+/// Change of a secret such as a password, recorded without its values.
+pub fn redacted_change(field: &str) -> Value {
+    json!({ field: { "from": REDACTED_VALUE, "to": REDACTED_VALUE } })
+}
+
+fn to_object<T: Serialize>(value: Option<&T>) -> Map<String, Value> {
+    match value.map(serde_json::to_value) {
+        Some(Ok(Value::Object(object))) => object,
+        _ => Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Account {
+        name: String,
+        password_hash: String,
+        tags: Vec<&'static str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        nickname: Option<String>,
+    }
+
+    fn account(name: &str, password_hash: &str) -> Account {
+        Account {
+            name: name.to_string(),
+            password_hash: password_hash.to_string(),
+            tags: vec!["a"],
+            nickname: None,
+        }
+    }
+
+    #[test]
+    fn diff_lists_only_changed_fields() {
+        let before = account("Jane", "hash");
+        let mut after = account("Janet", "hash");
+        after.tags.push("b");
+
+        assert_eq!(
+            diff(Some(&before), Some(&after)),
+            json!({
+                "name": { "from": "Jane", "to": "Janet" },
+                "tags": { "from": ["a"], "to": ["a", "b"] },
+            })
+        );
+        assert_eq!(diff(Some(&before), Some(&before)), json!({}));
+    }
+
+    #[test]
+    fn diff_covers_fields_only_on_one_side() {
+        let before = account("Jane", "hash");
+        let after = Account {
+            nickname: Some("JJ".to_string()),
+            ..account("Jane", "hash")
+        };
+
+        assert_eq!(
+            diff(Some(&before), Some(&after)),
+            json!({ "nickname": { "from": null, "to": "JJ" } })
+        );
+    }
+
+    #[test]
+    fn diff_of_a_creation_or_deletion_has_every_field() {
+        let created = diff(None, Some(&account("Jane", "hash")));
+        assert_eq!(created["name"], json!({ "from": null, "to": "Jane" }));
+        assert_eq!(created["tags"], json!({ "from": null, "to": ["a"] }));
+
+        let deleted = diff(Some(&account("Jane", "hash")), None);
+        assert_eq!(deleted["name"], json!({ "from": "Jane", "to": null }));
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        let changes = diff(
+            Some(&account("Jane", "old hash")),
+            Some(&account("Jane", "new hash")),
+        );
+        assert_eq!(
+            changes,
+            json!({ "password_hash": { "from": "[redacted]", "to": "[redacted]" } })
+        );
+
+        let created = diff(None, Some(&account("Jane", "hash")));
+        assert_eq!(
+            created["password_hash"],
+            json!({ "from": "[redacted]", "to": "[redacted]" })
+        );
+        assert!(!created.to_string().contains("\"hash\""));
+
+        assert_eq!(
+            redacted_change("totp_secret"),
+            json!({ "totp_secret": { "from": "[redacted]", "to": "[redacted]" } })
+        );
+    }
+
+    #[test]
+    fn actions_and_targets_have_stable_names() {
+        assert_eq!(AuditAction::TokenRevoked.as_str(), "token.revoked");
+        assert_eq!(
+            AuditTarget::PersonalAccessToken.as_str(),
+            "personal_access_token"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::header::USER_AGENT;
+use axum::http::request::Parts;
+use axum::http::HeaderMap;
+
+use super::core::AppState;
+
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// Address of the client that sent the request. `X-Forwarded-For` is only
+/// honoured when the peer is one of the configured trusted proxies, and the
+/// first address (from the right) that is not a trusted proxy wins.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for ClientIp {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app_state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let peer_ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+        Ok(ClientIp(resolve_client_ip(
+            peer_ip,
+            &parts.headers,
+            &app_state.config.trusted_proxies,
+        )))
+    }
+}
+
+fn resolve_client_ip(peer_ip: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> IpAddr {
+    if !trusted_proxies.contains(&peer_ip) {
+        return peer_ip;
+    }
+
+    let forwarded_ips = headers
+        .get_all(FORWARDED_FOR_HEADER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<IpAddr>>();
+
+    forwarded_ips
+        .iter()
+        .rev()
+        .find(|ip| !trusted_proxies.contains(ip))
+        .or(forwarded_ips.first())
+        .copied()
+        .unwrap_or(peer_ip)
+}
+
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// What we record about the device a session was started from.
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub ip: IpAddr,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for ClientInfo {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app_state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let ClientIp(ip) = ClientIp::from_request_parts(parts, app_state).await?;
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+        Ok(ClientInfo { ip, user_agent })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROXY: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    const INNER_PROXY: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    const PEER: IpAddr = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7));
+
+    fn forwarded_for(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(FORWARDED_FOR_HEADER, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn ignores_the_header_from_untrusted_peers() {
+        let headers = forwarded_for(&["203.0.113.9"]);
+
+        assert_eq!(resolve_client_ip(PEER, &headers, &[]), PEER);
+        assert_eq!(resolve_client_ip(PEER, &headers, &[PROXY]), PEER);
+    }
+
+    #[test]
+    fn takes_the_rightmost_untrusted_address() {
+        // the client can put anything in front, only what our proxies appended counts
+        let headers = forwarded_for(&["1.1.1.1, 203.0.113.9, 10.0.0.2"]);
+
+        assert_eq!(
+            resolve_client_ip(PROXY, &headers, &[PROXY, INNER_PROXY]),
+            ip("203.0.113.9")
+        );
+        assert_eq!(resolve_client_ip(PROXY, &headers, &[PROXY]), INNER_PROXY);
+    }
+
+    #[test]
+    fn joins_repeated_headers_in_order() {
+        let headers = forwarded_for(&["203.0.113.9", "2001:db8::1, 10.0.0.2"]);
+
+        assert_eq!(
+            resolve_client_ip(PROXY, &headers, &[PROXY, INNER_PROXY]),
+            ip("2001:db8::1")
+        );
+    }
+
+    #[test]
+    fn skips_garbage_and_falls_back_to_the_peer() {
+        let headers = forwarded_for(&["unknown, 203.0.113.9:443, , 203.0.113.10"]);
+        assert_eq!(
+            resolve_client_ip(PROXY, &headers, &[PROXY]),
+            ip("203.0.113.10")
+        );
+
+        assert_eq!(resolve_client_ip(PROXY, &HeaderMap::new(), &[PROXY]), PROXY);
+        assert_eq!(
+            resolve_client_ip(PROXY, &forwarded_for(&["garbage"]), &[PROXY]),
+            PROXY
+        );
+    }
+
+    #[test]
+    fn only_proxies_in_the_chain_yields_the_first() {
+        let headers = forwarded_for(&["10.0.0.2, 10.0.0.1"]);
+
+        assert_eq!(
+            resolve_client_ip(PROXY, &headers, &[PROXY, INNER_PROXY]),
+            INNER_PROXY
+        );
+    }
+}
@@ -1,14 +1,68 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use std::sync::Arc;
+
+use axum_extra::extract::cookie::SameSite;
 use sea_orm::DatabaseConnection;
 
+use crate::app::auth::cookies::RESERVED_COOKIE_NAMES;
+use crate::app::auth::keys::KeyStore;
+use crate::app::auth::login::throttle::LoginThrottle;
+use crate::app::auth::model::Role;
+use crate::app::auth::oidc::service::OidcClient;
+use crate::app::auth::password::{PasswordPolicy, MAX_PASSWORD_BYTES};
+use crate::app::auth::revocation::RevocationCache;
+use crate::app::auth::session::activity::SessionActivity;
+use crate::app::common::mailer::{Mailer, MailerKind};
+use crate::app::common::rate_limit::RateLimiter;
+
 pub struct AppState {
     pub db: DatabaseConnection,
     pub config: Config,
     pub public_bucket: Box<s3::bucket::Bucket>,
+    pub keys: KeyStore,
+    pub revocations: RevocationCache,
+    pub upload_quota: RateLimiter,
+    pub login_throttle: LoginThrottle,
+    pub mailer: Arc<dyn Mailer>,
+    /// Caps how many account emails (resets, links, ...) one address receives.
+    pub email_quota: RateLimiter,
+    /// Caps the passkey login challenges one client IP can have issued.
+    pub passkey_challenge_quota: RateLimiter,
+    /// Set when an OpenID Connect provider is configured.
+    pub oidc: Option<OidcClient>,
+    pub password_policy: PasswordPolicy,
+    pub session_activity: SessionActivity,
 }
 
+const DEFAULT_MEDIA_ALLOWED_TYPES: &str = "image/png,image/jpeg,image/webp,image/gif,image/avif";
+const DEFAULT_MEDIA_MAX_UPLOAD_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_MEDIA_UPLOADS_PER_HOUR: usize = 60;
+const DEFAULT_POST_TRASH_RETENTION_DAYS: i64 = 30;
+const DEFAULT_MAIL_FROM: &str = "GoWithDev <no-reply@gowithdev.in>";
+const DEFAULT_MAIL_DIR: &str = "mail";
+const DEFAULT_APP_BASE_URL: &str = "https://gowithdev.in";
+const DEFAULT_OIDC_SCOPES: &str = "openid email profile";
+/// Bounds bcrypt itself accepts.
+const MIN_BCRYPT_COST: u32 = 4;
+const MAX_BCRYPT_COST: u32 = 31;
+const DEFAULT_PASSWORD_MIN_LENGTH: usize = 8;
+const DEFAULT_PASSWORD_MIN_CHARACTER_CLASSES: usize = 1;
+const DEFAULT_BREACHED_PASSWORDS_FILE: &str = "data/breached-passwords.txt";
+const DEFAULT_SESSION_COOKIE_NAME: &str = "token";
+const DEFAULT_ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+const DEFAULT_SESSION_TTL_HOURS: i64 = 24;
+const DEFAULT_REMEMBER_ME_TTL_DAYS: i64 = 30;
+const MAX_ACCESS_TOKEN_TTL_MINUTES: i64 = 24 * 60;
+const MAX_REMEMBER_ME_TTL_DAYS: i64 = 365;
+
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub jwt_secret: String,
+    /// Directory of `<kid>.pem` Ed25519 keys used to sign and verify JWTs.
+    pub jwt_keys_dir: String,
+    /// Key id of the private key new tokens are signed with.
+    pub jwt_signing_key_id: String,
     pub database_url: String,
     pub b2_api_key_id: String,
     pub b2_api_secret: String,
@@ -17,12 +71,79 @@ pub struct Config {
     pub b2_region: String,
     pub host: String,
     pub port: u16,
+    pub media_allowed_types: Vec<String>,
+    pub media_max_upload_bytes: u64,
+    pub media_uploads_per_hour: usize,
+    /// Reverse proxies whose `X-Forwarded-For` header is trusted.
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Days a deleted post stays in the trash before it is purged, `None`
+    /// keeps it until purged by hand.
+    pub post_trash_retention_days: Option<i64>,
+    pub mailer: MailerKind,
+    pub smtp_url: Option<String>,
+    pub mail_from: String,
+    pub mail_dir: String,
+    /// Base URL of the frontend, used to build links sent by email.
+    pub app_base_url: String,
+    /// Domain passkeys are scoped to, defaults to the host of `app_base_url`.
+    pub webauthn_rp_id: String,
+    /// Origin the WebAuthn ceremonies run on, defaults to `app_base_url`.
+    pub webauthn_origin: String,
+    pub oidc: Option<OidcConfig>,
+    /// Whether anyone may sign up, otherwise accounts are created by invitation.
+    pub registration_open: bool,
+    /// Role of self-registered users.
+    pub registration_role: Role,
+    /// bcrypt cost of new hashes; older, cheaper hashes are upgraded on login.
+    pub bcrypt_cost: u32,
+    pub password_min_length: usize,
+    /// How many of lowercase, uppercase, digits and symbols a password needs.
+    pub password_min_character_classes: usize,
+    pub breached_passwords_file: String,
+    /// Fail at startup when the list is missing, only if it was configured explicitly.
+    pub breached_passwords_required: bool,
+    pub session: SessionConfig,
+}
+
+/// Session cookies and token lifetimes.
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// Name of the access token cookie.
+    pub cookie_name: String,
+    /// Off for local development over plain HTTP.
+    pub cookie_secure: bool,
+    pub cookie_same_site: SameSite,
+    /// Domain of the session cookies, e.g. `gowithdev.in` to share them between
+    /// `admin.` and `api.` subdomains. Host-only when unset.
+    pub cookie_domain: Option<String>,
+    pub access_token_ttl: chrono::Duration,
+    /// Lifetime of a session started without "remember me". Its cookies end
+    /// with the browser session.
+    pub session_ttl: chrono::Duration,
+    /// Lifetime of a session started with "remember me".
+    pub remember_me_ttl: chrono::Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// Issuer URL, the discovery document is read from
+    /// `{issuer}/.well-known/openid-configuration`.
+    pub issuer: String,
+    pub client_id: String,
+    /// Not needed for public clients, which rely on PKCE alone.
+    pub client_secret: Option<String>,
+    /// Our callback URL as registered with the provider.
+    pub redirect_url: String,
+    pub scopes: String,
+    /// Role given to users created on their first OIDC login.
+    pub default_role: Role,
 }
 
 impl Config {
     pub fn init() -> Result<Config, Box<dyn std::error::Error>> {
         let database_url = std::env::var("DATABASE_URL")?;
-        let jwt_secret = std::env::var("JWT_SECRET")?;
+        let jwt_keys_dir = std::env::var("JWT_KEYS_DIR")?;
+        let jwt_signing_key_id = std::env::var("JWT_SIGNING_KEY_ID")?;
         let b2_api_key_id = std::env::var("B2_API_KEY_ID")?;
         let b2_api_secret = std::env::var("B2_API_KEY")?;
         let b2_endpoint = std::env::var("B2_ENDPOINT")?;
@@ -32,9 +153,108 @@ impl Config {
         let host = std::env::var("HOST")?;
         let port = std::env::var("PORT")?.parse::<u16>()?;
 
+        let media_allowed_types = optional_env("MEDIA_ALLOWED_TYPES")
+            .unwrap_or_else(|| DEFAULT_MEDIA_ALLOWED_TYPES.to_string())
+            .split(',')
+            .map(|content_type| content_type.trim().to_lowercase())
+            .filter(|content_type| !content_type.is_empty())
+            .collect();
+        let media_max_upload_bytes =
+            parse_env_or("MEDIA_MAX_UPLOAD_BYTES", DEFAULT_MEDIA_MAX_UPLOAD_BYTES)?;
+        let media_uploads_per_hour =
+            parse_env_or("MEDIA_UPLOADS_PER_HOUR", DEFAULT_MEDIA_UPLOADS_PER_HOUR)?;
+
+        let trusted_proxies = optional_env("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|proxy| !proxy.is_empty())
+            .map(IpAddr::from_str)
+            .collect::<Result<Vec<IpAddr>, _>>()?;
+
+        let post_trash_retention_days = match parse_env_or(
+            "POST_TRASH_RETENTION_DAYS",
+            DEFAULT_POST_TRASH_RETENTION_DAYS,
+        )? {
+            0 => None,
+            days if days > 0 => Some(days),
+            _ => return Err("POST_TRASH_RETENTION_DAYS must not be negative".into()),
+        };
+
+        let mailer =
+            MailerKind::from_str(&optional_env("MAILER").unwrap_or_else(|| "stdout".to_string()))?;
+        let smtp_url = optional_env("SMTP_URL");
+        let mail_from = optional_env("MAIL_FROM").unwrap_or_else(|| DEFAULT_MAIL_FROM.to_string());
+        let mail_dir = optional_env("MAIL_DIR").unwrap_or_else(|| DEFAULT_MAIL_DIR.to_string());
+        let app_base_url = optional_env("APP_BASE_URL")
+            .unwrap_or_else(|| DEFAULT_APP_BASE_URL.to_string())
+            .trim_end_matches('/')
+            .to_string();
+
+        let webauthn_origin =
+            optional_env("WEBAUTHN_ORIGIN").unwrap_or_else(|| app_base_url.clone());
+        let webauthn_rp_id = match optional_env("WEBAUTHN_RP_ID") {
+            Some(rp_id) => rp_id,
+            None => url::Url::parse(&webauthn_origin)?
+                .host_str()
+                .ok_or("WEBAUTHN_ORIGIN has no host")?
+                .to_string(),
+        };
+
+        let oidc = match optional_env("OIDC_ISSUER") {
+            Some(issuer) => Some(OidcConfig {
+                issuer: issuer.trim_end_matches('/').to_string(),
+                client_id: std::env::var("OIDC_CLIENT_ID")?,
+                client_secret: optional_env("OIDC_CLIENT_SECRET"),
+                redirect_url: std::env::var("OIDC_REDIRECT_URL")?,
+                scopes: optional_env("OIDC_SCOPES")
+                    .unwrap_or_else(|| DEFAULT_OIDC_SCOPES.to_string()),
+                default_role: Role::from_str(
+                    &optional_env("OIDC_DEFAULT_ROLE").unwrap_or_else(|| "author".to_string()),
+                )?,
+            }),
+            None => None,
+        };
+
+        let registration_open = parse_env_or("REGISTRATION_OPEN", false)?;
+        let registration_role = Role::from_str(
+            &optional_env("REGISTRATION_ROLE").unwrap_or_else(|| "contributor".to_string()),
+        )?;
+
+        let bcrypt_cost = parse_env_or("BCRYPT_COST", bcrypt::DEFAULT_COST)?;
+        if !(MIN_BCRYPT_COST..=MAX_BCRYPT_COST).contains(&bcrypt_cost) {
+            return Err(format!(
+                "BCRYPT_COST must be between {} and {}",
+                MIN_BCRYPT_COST, MAX_BCRYPT_COST
+            )
+            .into());
+        }
+
+        let password_min_length = parse_env_or("PASSWORD_MIN_LENGTH", DEFAULT_PASSWORD_MIN_LENGTH)?;
+        if !(1..=MAX_PASSWORD_BYTES).contains(&password_min_length) {
+            return Err(format!(
+                "PASSWORD_MIN_LENGTH must be between 1 and {}",
+                MAX_PASSWORD_BYTES
+            )
+            .into());
+        }
+        let password_min_character_classes = parse_env_or(
+            "PASSWORD_MIN_CHARACTER_CLASSES",
+            DEFAULT_PASSWORD_MIN_CHARACTER_CLASSES,
+        )?;
+        if !(1..=4).contains(&password_min_character_classes) {
+            return Err("PASSWORD_MIN_CHARACTER_CLASSES must be between 1 and 4".into());
+        }
+        let breached_passwords_required = optional_env("BREACHED_PASSWORDS_FILE").is_some();
+        let breached_passwords_file = optional_env("BREACHED_PASSWORDS_FILE")
+            .unwrap_or_else(|| DEFAULT_BREACHED_PASSWORDS_FILE.to_string());
+
+        let session = SessionConfig::init()?;
+
         Ok(Config {
             database_url,
-            jwt_secret,
+            jwt_keys_dir,
+            jwt_signing_key_id,
             b2_api_key_id,
             b2_api_secret,
             b2_endpoint,
@@ -42,6 +262,137 @@ impl Config {
             b2_region,
             host,
             port,
+            media_allowed_types,
+            media_max_upload_bytes,
+            media_uploads_per_hour,
+            trusted_proxies,
+            post_trash_retention_days,
+            mailer,
+            smtp_url,
+            mail_from,
+            mail_dir,
+            app_base_url,
+            webauthn_rp_id,
+            webauthn_origin,
+            oidc,
+            registration_open,
+            registration_role,
+            bcrypt_cost,
+            password_min_length,
+            password_min_character_classes,
+            breached_passwords_file,
+            breached_passwords_required,
+            session,
         })
     }
 }
+
+impl SessionConfig {
+    fn init() -> Result<SessionConfig, Box<dyn std::error::Error>> {
+        let cookie_name = optional_env("SESSION_COOKIE_NAME")
+            .unwrap_or_else(|| DEFAULT_SESSION_COOKIE_NAME.to_string());
+        let is_token = cookie_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c));
+        if !is_token {
+            return Err(
+                "SESSION_COOKIE_NAME may only contain letters, digits and !#$%&'*+-.^_`|~".into(),
+            );
+        }
+        if RESERVED_COOKIE_NAMES.contains(&cookie_name.as_str()) {
+            return Err(format!(
+                "SESSION_COOKIE_NAME {} is used by another cookie",
+                cookie_name
+            )
+            .into());
+        }
+
+        let cookie_secure = parse_env_or("COOKIE_SECURE", true)?;
+        let cookie_same_site = match optional_env("COOKIE_SAME_SITE")
+            .map(|same_site| same_site.to_lowercase())
+            .as_deref()
+        {
+            None | Some("strict") => SameSite::Strict,
+            Some("lax") => SameSite::Lax,
+            Some("none") => SameSite::None,
+            Some(other) => {
+                return Err(format!(
+                    "COOKIE_SAME_SITE must be strict, lax or none, got {}",
+                    other
+                )
+                .into())
+            }
+        };
+        // browsers drop SameSite=None cookies that are not Secure
+        if cookie_same_site == SameSite::None && !cookie_secure {
+            return Err("COOKIE_SAME_SITE=none requires COOKIE_SECURE=true".into());
+        }
+        let cookie_domain = optional_env("COOKIE_DOMAIN");
+
+        let access_token_ttl_minutes =
+            parse_env_or("ACCESS_TOKEN_TTL_MINUTES", DEFAULT_ACCESS_TOKEN_TTL_MINUTES)?;
+        if !(1..=MAX_ACCESS_TOKEN_TTL_MINUTES).contains(&access_token_ttl_minutes) {
+            return Err(format!(
+                "ACCESS_TOKEN_TTL_MINUTES must be between 1 and {}",
+                MAX_ACCESS_TOKEN_TTL_MINUTES
+            )
+            .into());
+        }
+        let access_token_ttl = chrono::Duration::minutes(access_token_ttl_minutes);
+
+        // bounded before building the Duration, which panics when out of range
+        let session_ttl_hours = parse_env_or("SESSION_TTL_HOURS", DEFAULT_SESSION_TTL_HOURS)?;
+        if !(1..=MAX_REMEMBER_ME_TTL_DAYS * 24).contains(&session_ttl_hours) {
+            return Err(format!(
+                "SESSION_TTL_HOURS must be between 1 and {}",
+                MAX_REMEMBER_ME_TTL_DAYS * 24
+            )
+            .into());
+        }
+        let session_ttl = chrono::Duration::hours(session_ttl_hours);
+        if session_ttl < access_token_ttl {
+            return Err(
+                "SESSION_TTL_HOURS must not be shorter than ACCESS_TOKEN_TTL_MINUTES".into(),
+            );
+        }
+
+        let remember_me_ttl_days =
+            parse_env_or("REMEMBER_ME_TTL_DAYS", DEFAULT_REMEMBER_ME_TTL_DAYS)?;
+        if !(1..=MAX_REMEMBER_ME_TTL_DAYS).contains(&remember_me_ttl_days) {
+            return Err(format!(
+                "REMEMBER_ME_TTL_DAYS must be between 1 and {}",
+                MAX_REMEMBER_ME_TTL_DAYS
+            )
+            .into());
+        }
+        let remember_me_ttl = chrono::Duration::days(remember_me_ttl_days);
+        if remember_me_ttl < session_ttl {
+            return Err("REMEMBER_ME_TTL_DAYS must not be shorter than SESSION_TTL_HOURS".into());
+        }
+
+        Ok(SessionConfig {
+            cookie_name,
+            cookie_secure,
+            cookie_same_site,
+            cookie_domain,
+            access_token_ttl,
+            session_ttl,
+            remember_me_ttl,
+        })
+    }
+}
+
+fn optional_env(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+fn parse_env_or<T>(key: &str, default: T) -> Result<T, Box<dyn std::error::Error>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + 'static,
+{
+    match optional_env(key) {
+        Some(value) => Ok(value.parse::<T>()?),
+        None => Ok(default),
+    }
+}
@@ -10,6 +10,10 @@ impl AppError {
     pub fn new(status: axum::http::StatusCode, message: String) -> Self {
         Self { status, message }
     }
+
+    pub fn forbidden() -> Self {
+        Self::new(axum::http::StatusCode::FORBIDDEN, "Forbidden".to_string())
+    }
 }
 
 impl IntoResponse for AppError {
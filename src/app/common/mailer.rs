@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::async_trait;
+use axum::http::StatusCode;
+use lettre::message::Mailbox;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use uuid::Uuid;
+
+use super::core::Config;
+use super::errors::AppError;
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Outgoing email transport. Handlers only depend on this trait so local
+/// setups can write mails to disk or stdout instead of talking to SMTP.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MailerKind {
+    Smtp,
+    File,
+    Stdout,
+}
+
+impl FromStr for MailerKind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "smtp" => Ok(MailerKind::Smtp),
+            "file" => Ok(MailerKind::File),
+            "stdout" => Ok(MailerKind::Stdout),
+            _ => Err(format!("Unknown mailer: {}", kind)),
+        }
+    }
+}
+
+pub fn mailer_from_config(config: &Config) -> Result<Arc<dyn Mailer>, Box<dyn std::error::Error>> {
+    let mailer: Arc<dyn Mailer> = match config.mailer {
+        MailerKind::Smtp => {
+            let smtp_url = config
+                .smtp_url
+                .as_ref()
+                .ok_or("SMTP_URL is required when MAILER=smtp")?;
+            Arc::new(SmtpMailer {
+                transport: AsyncSmtpTransport::<Tokio1Executor>::from_url(smtp_url)?.build(),
+                from: config.mail_from.parse()?,
+            })
+        }
+        MailerKind::File => Arc::new(FileMailer {
+            directory: PathBuf::from(&config.mail_dir),
+        }),
+        MailerKind::Stdout => Arc::new(StdoutMailer),
+    };
+
+    Ok(mailer)
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(email.to.parse().map_err(map_to_app_error)?)
+            .subject(email.subject)
+            .body(email.body)
+            .map_err(map_to_app_error)?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(map_to_app_error)?;
+
+        Ok(())
+    }
+}
+
+/// Writes every mail to its own file, handy for local development and tests.
+pub struct FileMailer {
+    directory: PathBuf,
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        tokio::fs::create_dir_all(&self.directory)
+            .await
+            .map_err(map_to_app_error)?;
+
+        let path = self.directory.join(format!("{}.txt", Uuid::new_v4()));
+        tokio::fs::write(&path, format_email(&email))
+            .await
+            .map_err(map_to_app_error)?;
+
+        Ok(())
+    }
+}
+
+pub struct StdoutMailer;
+
+#[async_trait]
+impl Mailer for StdoutMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        println!("{}", format_email(&email));
+        Ok(())
+    }
+}
+
+fn format_email(email: &Email) -> String {
+    format!(
+        "To: {}\nSubject: {}\n\n{}\n",
+        email.to, email.subject, email.body
+    )
+}
+
+fn map_to_app_error(err: impl ToString) -> AppError {
+    AppError::new(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+}
@@ -1,2 +1,6 @@
+pub mod audit;
+pub mod client_ip;
 pub mod core;
 pub mod errors;
+pub mod mailer;
+pub mod rate_limit;
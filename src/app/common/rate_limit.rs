@@ -0,0 +1,146 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::http::header::RETRY_AFTER;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// Sliding window limiter allowing at most `limit` hits per key within `window`.
+pub struct RateLimiter {
+    limit: usize,
+    window: Duration,
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records a hit for `key`. When the limit is already reached nothing is
+    /// recorded and the time until the next hit is allowed is returned.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap();
+
+        self.remaining_wait(&mut hits, key, now)?;
+        hits.entry(key.to_string()).or_default().push_back(now);
+        Ok(())
+    }
+
+    /// Like [`RateLimiter::check`], but never records a hit. For work that may
+    /// still fail, followed by [`RateLimiter::record`] once it succeeded.
+    /// Concurrent callers can overshoot the limit by the requests in flight.
+    pub fn peek(&self, key: &str) -> Result<(), Duration> {
+        let mut hits = self.hits.lock().unwrap();
+        self.remaining_wait(&mut hits, key, Instant::now())
+    }
+
+    /// Records a hit for `key` regardless of the limit.
+    pub fn record(&self, key: &str) {
+        let mut hits = self.hits.lock().unwrap();
+        hits.entry(key.to_string())
+            .or_default()
+            .push_back(Instant::now());
+    }
+
+    /// Drops hits that left the window and returns how long `key` has to wait
+    /// when it has no hits left.
+    fn remaining_wait(
+        &self,
+        hits: &mut HashMap<String, VecDeque<Instant>>,
+        key: &str,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        if hits.len() >= MAX_TRACKED_KEYS {
+            hits.retain(|_, key_hits| {
+                key_hits
+                    .back()
+                    .is_some_and(|last| now.duration_since(*last) < self.window)
+            });
+        }
+
+        let Some(key_hits) = hits.get_mut(key) else {
+            return Ok(());
+        };
+        while key_hits
+            .front()
+            .is_some_and(|first| now.duration_since(*first) >= self.window)
+        {
+            key_hits.pop_front();
+        }
+
+        if key_hits.len() >= self.limit {
+            let oldest = key_hits.front().copied().unwrap_or(now);
+            return Err(self.window.saturating_sub(now.duration_since(oldest)));
+        }
+
+        Ok(())
+    }
+}
+
+/// `429 Too Many Requests` carrying a `Retry-After` header. `AppError` has no
+/// way to set headers, so handlers that can be throttled return this directly.
+pub fn too_many_requests(retry_after: Duration) -> Response {
+    let seconds = retry_after.as_secs().max(1);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(RETRY_AFTER, seconds.to_string())],
+        "Too many requests".to_string(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_allows_up_to_the_limit_per_key() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+
+        assert!(limiter.check("a").is_ok());
+        assert!(limiter.check("a").is_ok());
+        let retry_after = limiter.check("a").unwrap_err();
+        assert!(retry_after > Duration::from_secs(59) && retry_after <= Duration::from_secs(60));
+
+        assert!(limiter.check("b").is_ok());
+    }
+
+    #[test]
+    fn hits_expire_with_the_window() {
+        let limiter = RateLimiter::new(1, Duration::from_millis(20));
+
+        assert!(limiter.check("a").is_ok());
+        assert!(limiter.check("a").is_err());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(limiter.check("a").is_ok());
+    }
+
+    #[test]
+    fn peek_does_not_use_up_hits() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+
+        assert!(limiter.peek("a").is_ok());
+        assert!(limiter.peek("a").is_ok());
+
+        limiter.record("a");
+        assert!(limiter.peek("a").is_err());
+        assert!(limiter.check("a").is_err());
+    }
+
+    #[test]
+    fn too_many_requests_rounds_retry_after_up_to_a_second() {
+        let response = too_many_requests(Duration::from_millis(10));
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "1");
+    }
+}
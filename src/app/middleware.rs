@@ -1,19 +1,26 @@
+use std::str::FromStr;
 use std::sync::Arc;
 
 use axum::{
     extract::{Request, State},
-    http::StatusCode,
+    http::{header::AUTHORIZATION, Method, StatusCode},
     middleware::Next,
     response::Response,
+    Extension,
 };
 use axum_extra::extract::CookieJar;
+use sea_orm::prelude::Uuid;
 
 use super::{
-    auth::model::{Claims, UserInfo},
+    auth::{
+        cookies::CSRF_TOKEN_HEADER_NAME,
+        model::{AuthMethod, Permission, UserInfo},
+        personal_access_token::{authenticate_personal_access_token, PERSONAL_ACCESS_TOKEN_PREFIX},
+        token::{decode_access_token, hash_token},
+    },
     common::{core::AppState, errors::AppError},
 };
 
-const TOKEN_COOKIE_NAME: &str = "token";
 const UNAUTHORIZED_ERROR: &str = "Unauthorized";
 
 pub async fn authenticate(
@@ -22,23 +29,209 @@ pub async fn authenticate(
     mut req: Request,
     next: Next,
 ) -> Result<Response, AppError> {
-    let token = jar
-        .get(TOKEN_COOKIE_NAME)
-        .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, UNAUTHORIZED_ERROR.to_string()))?
-        .value()
-        .to_string();
-
-    let token_data = jsonwebtoken::decode::<Claims>(
-        &token,
-        &jsonwebtoken::DecodingKey::from_secret(app_state.config.jwt_secret.as_ref()),
-        &jsonwebtoken::Validation::default(),
-    )
-    .map_err(|_| AppError::new(StatusCode::UNAUTHORIZED, UNAUTHORIZED_ERROR.to_string()))?;
-
-    req.extensions_mut().insert(UserInfo {
-        id: token_data.claims.sub,
-    });
+    // an explicit `Authorization` header wins over the cookie
+    let bearer_token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+
+    let user_info = match bearer_token {
+        Some(token) if token.starts_with(PERSONAL_ACCESS_TOKEN_PREFIX) => {
+            authenticate_personal_access_token(&app_state, &token).await?
+        }
+        Some(token) => {
+            authenticate_access_token(&app_state, &token, AuthMethod::BearerToken).await?
+        }
+        None => match jar.get(&app_state.config.session.cookie_name) {
+            Some(cookie) => {
+                authenticate_access_token(&app_state, cookie.value(), AuthMethod::Cookie).await?
+            }
+            None => None,
+        },
+    };
+
+    let user_info = user_info
+        .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, UNAUTHORIZED_ERROR.to_string()))?;
+
+    req.extensions_mut().insert(user_info);
 
     let response = next.run(req).await;
     Ok(response)
 }
+
+async fn authenticate_access_token(
+    app_state: &AppState,
+    token: &str,
+    auth_method: AuthMethod,
+) -> Result<Option<UserInfo>, AppError> {
+    let Some(claims) = decode_access_token(token, app_state) else {
+        return Ok(None);
+    };
+
+    if app_state
+        .revocations
+        .is_revoked(&app_state.db, &claims)
+        .await?
+    {
+        return Ok(None);
+    }
+
+    let session_id = Uuid::from_str(&claims.sid).ok();
+    if let Some(session_id) = session_id {
+        app_state.session_activity.touch(session_id);
+    }
+
+    Ok(Some(UserInfo {
+        id: claims.sub,
+        role: claims.role,
+        auth_method,
+        scopes: None,
+        csrf_token_hash: claims.csrf,
+        session_id,
+    }))
+}
+
+/// Requires cookie-authenticated requests that change state to echo the
+/// session's CSRF token (from the `csrf_token` cookie) in `X-CSRF-Token`.
+/// Requests carrying an `Authorization` header can not be forged by another
+/// site and are exempt. Must run inside [`authenticate`].
+pub async fn verify_csrf_token(
+    Extension(user_info): Extension<UserInfo>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let is_mutation = matches!(
+        *req.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+
+    if !is_mutation || user_info.auth_method != AuthMethod::Cookie {
+        return Ok(next.run(req).await);
+    }
+
+    let header_token = req
+        .headers()
+        .get(CSRF_TOKEN_HEADER_NAME)
+        .and_then(|value| value.to_str().ok());
+
+    let valid = match (header_token, &user_info.csrf_token_hash) {
+        (Some(token), Some(expected_hash)) => hash_token(token) == *expected_hash,
+        _ => false,
+    };
+
+    if !valid {
+        tracing::warn!(
+            "csrf check failed | user {} | {} {}",
+            user_info.id,
+            req.method(),
+            req.uri().path()
+        );
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "Missing or invalid CSRF token".to_string(),
+        ));
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Rejects the request with a 403 unless the authenticated user's role grants
+/// `permission`. Must run inside [`authenticate`].
+pub async fn require_permission(
+    State(permission): State<Permission>,
+    Extension(user_info): Extension<UserInfo>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if !user_info.has_permission(permission) {
+        tracing::warn!(
+            "permission denied | user {} | {}",
+            user_info.id,
+            permission.as_str()
+        );
+        return Err(AppError::forbidden());
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use axum::Router;
+
+    use super::*;
+
+    const CSRF_TOKEN: &str = "csrf-token";
+
+    fn user(auth_method: AuthMethod) -> UserInfo {
+        UserInfo {
+            csrf_token_hash: Some(hash_token(CSRF_TOKEN)),
+            ..UserInfo::for_tests(crate::app::auth::model::Role::Author, auth_method)
+        }
+    }
+
+    /// Serves a route behind [`verify_csrf_token`] as `user_info` and returns
+    /// its base URL.
+    async fn serve(user_info: UserInfo) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let router = Router::new()
+            .route("/", get(|| async { "ok" }).post(|| async { "ok" }))
+            .layer(axum::middleware::from_fn(verify_csrf_token))
+            .layer(Extension(user_info));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        base_url
+    }
+
+    async fn status(request: reqwest::RequestBuilder) -> reqwest::StatusCode {
+        request.send().await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn cookie_mutations_need_the_session_token() {
+        let url = serve(user(AuthMethod::Cookie)).await;
+        let client = reqwest::Client::new();
+
+        assert_eq!(
+            status(client.post(&url)).await,
+            reqwest::StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(client.post(&url).header(CSRF_TOKEN_HEADER_NAME, "forged")).await,
+            reqwest::StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(client.post(&url).header(CSRF_TOKEN_HEADER_NAME, CSRF_TOKEN)).await,
+            reqwest::StatusCode::OK
+        );
+        assert_eq!(status(client.get(&url)).await, reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn sessions_without_a_token_reject_every_mutation() {
+        let url = serve(UserInfo {
+            csrf_token_hash: None,
+            ..user(AuthMethod::Cookie)
+        })
+        .await;
+
+        let request = reqwest::Client::new()
+            .post(&url)
+            .header(CSRF_TOKEN_HEADER_NAME, CSRF_TOKEN);
+        assert_eq!(status(request).await, reqwest::StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn header_authenticated_requests_are_exempt() {
+        for auth_method in [AuthMethod::BearerToken, AuthMethod::PersonalAccessToken] {
+            let url = serve(user(auth_method)).await;
+            let request = reqwest::Client::new().post(&url);
+            assert_eq!(status(request).await, reqwest::StatusCode::OK);
+        }
+    }
+}
@@ -1,8 +1,20 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub mod audit_events;
+pub mod invitations;
+pub mod login_lockouts;
+pub mod mfa_recovery_codes;
+pub mod personal_access_tokens;
 pub mod post_categories;
 pub mod post_comments;
+pub mod post_revisions;
 pub mod post_tags;
 pub mod posts;
+pub mod revoked_tokens;
+pub mod session_refresh_tokens;
+pub mod sessions;
 pub mod tags;
+pub mod user_tokens;
 pub mod users;
+pub mod webauthn_challenges;
+pub mod webauthn_credentials;
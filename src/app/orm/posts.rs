@@ -19,9 +19,13 @@ pub struct Model {
     #[sea_orm(column_type = "Text", unique)]
     pub slug: String,
     pub author: Uuid,
-    pub draft: Option<bool>,
+    pub draft: bool,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: Option<DateTimeWithTimeZone>,
+    pub published_at: Option<DateTimeWithTimeZone>,
+    pub publish_at: Option<DateTimeWithTimeZone>,
+    pub unpublish_at: Option<DateTimeWithTimeZone>,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
     pub read_time_millis: i64,
     #[sea_orm(column_type = "Text")]
     pub cover_image: String,
@@ -20,14 +20,57 @@ pub struct Model {
     pub created_at: DateTimeWithTimeZone,
     #[sea_orm(column_type = "Text", nullable)]
     pub avatar: Option<String>,
+    pub tokens_valid_after: Option<DateTimeWithTimeZone>,
+    #[sea_orm(column_type = "Text")]
+    pub role: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTimeWithTimeZone>,
+    pub totp_last_used_step: Option<i64>,
+    pub passwordless_enabled: bool,
+    pub email_verified_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::invitations::Entity")]
+    Invitations,
+    #[sea_orm(has_many = "super::mfa_recovery_codes::Entity")]
+    MfaRecoveryCodes,
+    #[sea_orm(has_many = "super::personal_access_tokens::Entity")]
+    PersonalAccessTokens,
     #[sea_orm(has_many = "super::post_comments::Entity")]
     PostComments,
     #[sea_orm(has_many = "super::posts::Entity")]
     Posts,
+    #[sea_orm(has_many = "super::revoked_tokens::Entity")]
+    RevokedTokens,
+    #[sea_orm(has_many = "super::sessions::Entity")]
+    Sessions,
+    #[sea_orm(has_many = "super::user_tokens::Entity")]
+    UserTokens,
+    #[sea_orm(has_many = "super::webauthn_challenges::Entity")]
+    WebauthnChallenges,
+    #[sea_orm(has_many = "super::webauthn_credentials::Entity")]
+    WebauthnCredentials,
+}
+
+impl Related<super::invitations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Invitations.def()
+    }
+}
+
+impl Related<super::mfa_recovery_codes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MfaRecoveryCodes.def()
+    }
+}
+
+impl Related<super::personal_access_tokens::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PersonalAccessTokens.def()
+    }
 }
 
 impl Related<super::post_comments::Entity> for Entity {
@@ -42,4 +85,34 @@ impl Related<super::posts::Entity> for Entity {
     }
 }
 
+impl Related<super::revoked_tokens::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RevokedTokens.def()
+    }
+}
+
+impl Related<super::sessions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Sessions.def()
+    }
+}
+
+impl Related<super::user_tokens::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserTokens.def()
+    }
+}
+
+impl Related<super::webauthn_challenges::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WebauthnChallenges.def()
+    }
+}
+
+impl Related<super::webauthn_credentials::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WebauthnCredentials.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
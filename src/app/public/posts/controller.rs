@@ -2,7 +2,12 @@ use axum::{
     extract::{Path, State},
     Json,
 };
-use sea_orm::{prelude::Uuid, EntityTrait, LoaderTrait, ModelTrait, QuerySelect};
+use sea_orm::{
+    prelude::{DateTimeWithTimeZone, Uuid},
+    sea_query::Expr,
+    ColumnTrait, Condition, EntityTrait, LoaderTrait, ModelTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
 
 use std::sync::Arc;
 
@@ -26,7 +31,12 @@ pub async fn get_posts(
             PostColumn::Summary,
             PostColumn::CoverImage,
             PostColumn::ReadTimeMillis,
+            PostColumn::PublishedAt,
+            PostColumn::PublishAt,
+            PostColumn::UnpublishAt,
         ])
+        .filter(visible_posts())
+        .order_by_desc(Expr::cust("COALESCE(published_at, publish_at)"))
         .all(&app_state.db)
         .await?;
 
@@ -54,7 +64,11 @@ pub async fn get_post(
             PostColumn::Content,
             PostColumn::Summary,
             PostColumn::UpdatedAt,
+            PostColumn::PublishedAt,
+            PostColumn::PublishAt,
+            PostColumn::UnpublishAt,
         ])
+        .filter(visible_posts())
         .one(&app_state.db)
         .await?;
 
@@ -72,5 +86,24 @@ pub async fn get_post(
         .await
         .unwrap();
 
-    return Ok(Json(Some(PostResponse::new(&post, &tags))));
+    Ok(Json(Some(PostResponse::new(&post, &tags))))
+}
+
+/// Posts readers may see. Checks `publish_at` and `unpublish_at` directly, so
+/// a late scheduler run never keeps a post back or up longer than planned.
+fn visible_posts() -> Condition {
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+    Condition::all()
+        .add(PostColumn::DeletedAt.is_null())
+        .add(
+            Condition::any()
+                .add(PostColumn::PublishedAt.is_not_null())
+                .add(PostColumn::PublishAt.lte(now)),
+        )
+        .add(
+            Condition::any()
+                .add(PostColumn::UnpublishAt.is_null())
+                .add(PostColumn::UnpublishAt.gt(now)),
+        )
 }
@@ -10,20 +10,22 @@ pub struct PostResponse {
     content: String,
     summary: String,
     updated_at: DateTimeWithTimeZone,
+    published_at: Option<DateTimeWithTimeZone>,
     tags: Vec<String>,
     cover_image: String,
     read_time_millis: i64,
 }
 
 impl PostResponse {
-    pub fn new(post: &Post, tags: &Vec<Tag>) -> Self {
+    pub fn new(post: &Post, tags: &[Tag]) -> Self {
         PostResponse {
             id: post.id,
             title: post.title.clone(),
             content: post.content.clone(),
             summary: post.summary.clone(),
             updated_at: post.updated_at.unwrap(),
-            tags: tags.into_iter().map(|tag| tag.name.clone()).collect(),
+            published_at: post.published_at.or(post.publish_at),
+            tags: tags.iter().map(|tag| tag.name.clone()).collect(),
             cover_image: post.cover_image.clone(),
             read_time_millis: post.read_time_millis,
         }
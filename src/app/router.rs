@@ -5,14 +5,15 @@ use axum::extract::{MatchedPath, Request};
 use axum::http::StatusCode;
 use axum::middleware::{self};
 use axum::response::{IntoResponse, Response};
-use axum::routing::post;
+use axum::routing::{delete, patch, post, put};
 use axum::{routing::get, Router};
 use tower_http::trace::TraceLayer;
 use tracing::{info_span, Span};
 
 use crate::AppState;
 
-use super::middleware::authenticate;
+use super::auth::model::Permission;
+use super::middleware::{authenticate, require_permission, verify_csrf_token};
 use super::{auth, public, secure};
 
 pub fn create_router(app_state: Arc<AppState>) -> Router {
@@ -45,31 +46,242 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
             );
         });
 
-    return Router::new()
-        .route("/admin/posts", post(secure::posts::controller::create_post))
+    Router::new()
+        .route(
+            "/admin/posts",
+            post(secure::posts::controller::create_post).route_layer(
+                middleware::from_fn_with_state(Permission::PostsWrite, require_permission),
+            ),
+        )
         .route(
             "/admin/posts/:id",
-            get(secure::posts::controller::get_post).post(secure::posts::controller::update_post),
+            get(secure::posts::controller::get_post)
+                .route_layer(middleware::from_fn_with_state(
+                    Permission::PostsRead,
+                    require_permission,
+                ))
+                .merge(post(secure::posts::controller::update_post).route_layer(
+                    middleware::from_fn_with_state(Permission::PostsWrite, require_permission),
+                ))
+                .merge(patch(secure::posts::controller::patch_post).route_layer(
+                    middleware::from_fn_with_state(Permission::PostsWrite, require_permission),
+                ))
+                .merge(delete(secure::posts::controller::delete_post).route_layer(
+                    middleware::from_fn_with_state(Permission::PostsWrite, require_permission),
+                )),
+        )
+        .route(
+            "/admin/posts/trash",
+            get(secure::posts::controller::get_trash).route_layer(middleware::from_fn_with_state(
+                Permission::PostsRead,
+                require_permission,
+            )),
+        )
+        .route(
+            "/admin/posts/:id/restore",
+            post(secure::posts::controller::restore_post).route_layer(
+                middleware::from_fn_with_state(Permission::PostsWrite, require_permission),
+            ),
+        )
+        .route(
+            "/admin/posts/:id/purge",
+            delete(secure::posts::controller::purge_post).route_layer(
+                middleware::from_fn_with_state(Permission::PostsPurge, require_permission),
+            ),
+        )
+        .route(
+            "/admin/posts/:id/publish",
+            post(secure::posts::controller::publish_post).route_layer(
+                middleware::from_fn_with_state(Permission::PostsPublish, require_permission),
+            ),
+        )
+        .route(
+            "/admin/posts/:id/unpublish",
+            post(secure::posts::controller::unpublish_post).route_layer(
+                middleware::from_fn_with_state(Permission::PostsPublish, require_permission),
+            ),
+        )
+        .route(
+            "/admin/posts/:id/revisions",
+            get(secure::revisions::controller::get_revisions).route_layer(
+                middleware::from_fn_with_state(Permission::PostsRead, require_permission),
+            ),
+        )
+        .route(
+            "/admin/posts/:id/revisions/diff",
+            get(secure::revisions::controller::get_revision_diff).route_layer(
+                middleware::from_fn_with_state(Permission::PostsRead, require_permission),
+            ),
+        )
+        .route(
+            "/admin/posts/:id/revisions/:revision/restore",
+            post(secure::revisions::controller::restore_revision).route_layer(
+                middleware::from_fn_with_state(Permission::PostsWrite, require_permission),
+            ),
         )
         .route(
             "/admin/categories",
-            get(secure::categories::controller::get_categories),
+            get(secure::categories::controller::get_categories).route_layer(
+                middleware::from_fn_with_state(Permission::CategoriesRead, require_permission),
+            ),
+        )
+        .route(
+            "/admin/security/lockouts",
+            get(secure::security::controller::get_lockouts).route_layer(
+                middleware::from_fn_with_state(Permission::UsersManage, require_permission),
+            ),
+        )
+        .route(
+            "/admin/audit-events",
+            get(secure::audit::controller::get_audit_events).route_layer(
+                middleware::from_fn_with_state(Permission::UsersManage, require_permission),
+            ),
+        )
+        .route(
+            "/admin/invitations",
+            get(secure::invitations::controller::get_invitations)
+                .post(secure::invitations::controller::create_invitation)
+                .route_layer(middleware::from_fn_with_state(
+                    Permission::UsersManage,
+                    require_permission,
+                )),
+        )
+        .route(
+            "/admin/invitations/:id",
+            delete(secure::invitations::controller::revoke_invitation).route_layer(
+                middleware::from_fn_with_state(Permission::UsersManage, require_permission),
+            ),
+        )
+        .route(
+            "/media/pre-signed-url",
+            get(secure::media::controller::get_presigned_url).route_layer(
+                middleware::from_fn_with_state(Permission::MediaUpload, require_permission),
+            ),
         )
         .route("/me", get(secure::user::controller::get_current_user_info))
+        .route(
+            "/me/password",
+            put(secure::user::controller::change_password),
+        )
+        .route("/me/2fa/setup", post(auth::mfa::controller::setup_mfa))
+        .route("/me/2fa/enable", post(auth::mfa::controller::enable_mfa))
+        .route("/me/2fa/disable", post(auth::mfa::controller::disable_mfa))
+        .route(
+            "/me/2fa/recovery-codes",
+            post(auth::mfa::controller::regenerate_recovery_codes),
+        )
+        .route("/me/passkeys", get(auth::passkey::controller::get_passkeys))
+        .route(
+            "/me/passkeys/options",
+            post(auth::passkey::controller::registration_options),
+        )
+        .route(
+            "/me/passkeys/register",
+            post(auth::passkey::controller::register_passkey),
+        )
+        .route(
+            "/me/passkeys/:id",
+            delete(auth::passkey::controller::delete_passkey),
+        )
+        .route(
+            "/me/passwordless",
+            put(auth::magic_link::controller::update_passwordless),
+        )
+        .route(
+            "/me/tokens",
+            get(secure::tokens::controller::get_tokens)
+                .post(secure::tokens::controller::create_token),
+        )
+        .route(
+            "/me/tokens/:id",
+            delete(secure::tokens::controller::revoke_token),
+        )
+        .route(
+            "/me/sessions",
+            get(secure::sessions::controller::get_sessions),
+        )
+        .route(
+            "/me/sessions/revoke-others",
+            post(secure::sessions::controller::revoke_other_sessions),
+        )
+        .route(
+            "/me/sessions/:id",
+            delete(secure::sessions::controller::revoke_user_session),
+        )
+        .route(
+            "/logout/all",
+            post(auth::logout::controller::logout_everywhere),
+        )
+        .layer(middleware::from_fn(verify_csrf_token))
         .layer(middleware::from_fn_with_state(
             app_state.clone(),
             authenticate,
         ))
         .route("/login", post(auth::login::controller::verify_login))
-        .route("/posts", get(public::posts::controller::get_posts))
-        .route("/posts/:id", get(public::posts::controller::get_post))
         .route(
-            "/media/pre-signed-url",
-            get(secure::media::controller::get_presigned_url),
+            "/.well-known/jwks.json",
+            get(auth::jwks::controller::get_jwks),
+        )
+        .route("/login/mfa", post(auth::mfa::controller::verify_login_mfa))
+        .route(
+            "/auth/magic-link",
+            post(auth::magic_link::controller::request_magic_link),
+        )
+        .route(
+            "/auth/magic-link/verify",
+            post(auth::magic_link::controller::verify_magic_link),
+        )
+        .route(
+            "/auth/passkey/options",
+            post(auth::passkey::controller::login_options),
+        )
+        .route(
+            "/auth/passkey/verify",
+            post(auth::passkey::controller::verify_passkey_login),
+        )
+        .route(
+            "/auth/oidc/login",
+            get(auth::oidc::controller::start_oidc_login),
+        )
+        .route(
+            "/auth/oidc/callback",
+            get(auth::oidc::controller::oidc_callback),
+        )
+        .route("/auth/refresh", post(auth::session::controller::refresh))
+        .route("/logout", post(auth::logout::controller::logout))
+        .route(
+            "/auth/register",
+            post(auth::registration::controller::register),
+        )
+        .route(
+            "/auth/verify-email",
+            post(auth::registration::controller::verify_email),
+        )
+        .route(
+            "/auth/verify-email/resend",
+            post(auth::registration::controller::resend_verification),
         )
+        .route(
+            "/auth/invitations/accept",
+            post(auth::registration::controller::accept_invitation),
+        )
+        .route(
+            "/auth/secure-account",
+            post(auth::secure_account::controller::secure_account),
+        )
+        .route(
+            "/auth/password/forgot",
+            post(auth::password_reset::controller::forgot_password),
+        )
+        .route(
+            "/auth/password/reset",
+            post(auth::password_reset::controller::reset_password),
+        )
+        .route("/posts", get(public::posts::controller::get_posts))
+        .route("/posts/:id", get(public::posts::controller::get_post))
         .fallback(handler_404)
         .layer(trace_layer)
-        .with_state(app_state);
+        .with_state(app_state)
 }
 
 async fn handler_404() -> impl IntoResponse {
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder};
+
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::audit_events::{Column as AuditEventColumn, Entity as AuditEvents};
+
+use super::model::{AuditEventPage, AuditEventQuery};
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 200;
+
+/// Newest first, narrowed down by any combination of the query filters.
+pub async fn get_audit_events(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<AuditEventQuery>,
+) -> Result<Json<AuditEventPage>, AppError> {
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    if page == 0 || !(1..=MAX_PAGE_SIZE).contains(&per_page) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "page must be at least 1 and per_page between 1 and {}",
+                MAX_PAGE_SIZE
+            ),
+        ));
+    }
+
+    let mut select = AuditEvents::find();
+    if let Some(action) = query.action {
+        select = select.filter(AuditEventColumn::Action.eq(action));
+    }
+    if let Some(actor_id) = query.actor_id {
+        select = select.filter(AuditEventColumn::ActorId.eq(actor_id));
+    }
+    if let Some(target_type) = query.target_type {
+        select = select.filter(AuditEventColumn::TargetType.eq(target_type));
+    }
+    if let Some(target_id) = query.target_id {
+        select = select.filter(AuditEventColumn::TargetId.eq(target_id));
+    }
+    if let Some(from) = query.from {
+        select = select.filter(AuditEventColumn::CreatedAt.gte(from));
+    }
+    if let Some(to) = query.to {
+        select = select.filter(AuditEventColumn::CreatedAt.lt(to));
+    }
+
+    let paginator = select
+        .order_by_desc(AuditEventColumn::CreatedAt)
+        .order_by_desc(AuditEventColumn::Id)
+        .paginate(&app_state.db, per_page);
+
+    let total = paginator.num_items().await?;
+    let events = paginator.fetch_page(page - 1).await?;
+
+    Ok(Json(AuditEventPage {
+        events,
+        page,
+        per_page,
+        total,
+    }))
+}
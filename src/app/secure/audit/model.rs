@@ -0,0 +1,27 @@
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use serde::{Deserialize, Serialize};
+
+use crate::app::orm::audit_events::Model as AuditEvent;
+
+#[derive(Debug, Deserialize)]
+pub struct AuditEventQuery {
+    pub action: Option<String>,
+    pub actor_id: Option<Uuid>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    /// Only events at or after this time.
+    pub from: Option<DateTimeWithTimeZone>,
+    /// Only events before this time.
+    pub to: Option<DateTimeWithTimeZone>,
+    /// Starts at 1.
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEventPage {
+    pub events: Vec<AuditEvent>,
+    pub page: u64,
+    pub per_page: u64,
+    pub total: u64,
+}
@@ -0,0 +1,161 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+use crate::app::auth::model::UserInfo;
+use crate::app::auth::registration::service::{email_taken, normalize_email};
+use crate::app::auth::token::{generate_opaque_token, hash_token};
+use crate::app::common::audit::{diff, AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::ClientIp;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::common::mailer::Email;
+use crate::app::orm::invitations::{
+    ActiveModel as InvitationActiveModel, Column as InvitationColumn, Entity as Invitations,
+};
+
+use super::model::{CreateInvitationRequest, InvitationResponse};
+
+const INVITATION_TTL_DAYS: i64 = 7;
+
+/// Invites someone by email with a pre-assigned role. Earlier pending
+/// invitations for the same address stop working.
+pub async fn create_invitation(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+    Json(payload): Json<CreateInvitationRequest>,
+) -> Result<Json<InvitationResponse>, AppError> {
+    let email = normalize_email(&payload.email)?;
+
+    if email_taken(&app_state, &email).await? {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "An account with this email already exists".to_string(),
+        ));
+    }
+
+    Invitations::delete_many()
+        .filter(InvitationColumn::Email.eq(&email))
+        .filter(InvitationColumn::AcceptedAt.is_null())
+        .exec(&app_state.db)
+        .await?;
+
+    let token = generate_opaque_token();
+    let expires_at = chrono::Utc::now() + chrono::Duration::days(INVITATION_TTL_DAYS);
+
+    let invitation = InvitationActiveModel {
+        email: ActiveValue::Set(email),
+        role: ActiveValue::Set(payload.role.as_str().to_string()),
+        invited_by: ActiveValue::Set(Some(Uuid::from_str(&user_info.id).unwrap())),
+        token_hash: ActiveValue::Set(hash_token(&token)),
+        expires_at: ActiveValue::Set(expires_at.into()),
+        ..Default::default()
+    }
+    .insert(&app_state.db)
+    .await?;
+
+    let accept_url = format!(
+        "{}/accept-invitation?token={}",
+        app_state.config.app_base_url, token
+    );
+
+    app_state
+        .mailer
+        .send(Email {
+            to: invitation.email.clone(),
+            subject: "You have been invited to GoWithDev".to_string(),
+            body: format!(
+                "Hi,\n\n\
+                You have been invited to join GoWithDev as {} {}. Use the link below \
+                to choose your name and password. It expires in {} days.\n\n{}",
+                article(&invitation.role),
+                invitation.role,
+                INVITATION_TTL_DAYS,
+                accept_url
+            ),
+        })
+        .await?;
+
+    tracing::info!(
+        "invitation sent | {} | {} | by {}",
+        invitation.id,
+        invitation.role,
+        user_info.id
+    );
+
+    let invitation = InvitationResponse::from(invitation);
+    AuditEvent::new(AuditAction::Created)
+        .actor(Uuid::from_str(&user_info.id).unwrap())
+        .ip(client_ip)
+        .target(AuditTarget::Invitation, invitation.id)
+        .changes(diff(None, Some(&invitation)))
+        .record(&app_state.db)
+        .await;
+
+    Ok(Json(invitation))
+}
+
+pub async fn get_invitations(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<InvitationResponse>>, AppError> {
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+    let invitations = Invitations::find()
+        .filter(InvitationColumn::AcceptedAt.is_null())
+        .filter(InvitationColumn::ExpiresAt.gt(now))
+        .order_by_desc(InvitationColumn::CreatedAt)
+        .all(&app_state.db)
+        .await?;
+
+    Ok(Json(invitations.into_iter().map(Into::into).collect()))
+}
+
+pub async fn revoke_invitation(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    let not_found = || AppError::new(StatusCode::NOT_FOUND, "Invitation not found".to_string());
+
+    let invitation = Invitations::find_by_id(id)
+        .filter(InvitationColumn::AcceptedAt.is_null())
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(not_found)?;
+
+    let deleted = Invitations::delete_many()
+        .filter(InvitationColumn::Id.eq(id))
+        .filter(InvitationColumn::AcceptedAt.is_null())
+        .exec(&app_state.db)
+        .await?;
+
+    // accepted in the meantime
+    if deleted.rows_affected == 0 {
+        return Err(not_found());
+    }
+
+    AuditEvent::new(AuditAction::Deleted)
+        .actor(Uuid::from_str(&user_info.id).unwrap())
+        .ip(client_ip)
+        .target(AuditTarget::Invitation, id)
+        .changes(diff(Some(&InvitationResponse::from(invitation)), None))
+        .record(&app_state.db)
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn article(role: &str) -> &'static str {
+    if role.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        "an"
+    } else {
+        "a"
+    }
+}
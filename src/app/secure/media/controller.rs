@@ -1,12 +1,25 @@
+use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, Json};
+use axum::extract::Query;
+use axum::response::{IntoResponse, Response};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use http_02::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use http_02::{HeaderMap, HeaderValue};
 
+use serde_json::json;
 use uuid::Uuid;
 
+use crate::app::auth::model::UserInfo;
+use crate::app::common::audit::{AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::ClientIp;
+use crate::app::common::rate_limit::too_many_requests;
 use crate::app::common::{core::AppState, errors::AppError};
 
-use super::model::PreSignedUrlResponse;
+use super::model::{PreSignedUrlRequest, PreSignedUrlResponse};
+
+const PRESIGNED_URL_EXPIRY_SECS: u32 = 60;
 
 fn map_to_app_error(err: impl ToString) -> AppError {
     AppError {
@@ -17,17 +30,70 @@ fn map_to_app_error(err: impl ToString) -> AppError {
 
 pub async fn get_presigned_url(
     State(app_state): State<Arc<AppState>>,
-) -> Result<Json<PreSignedUrlResponse>, AppError> {
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+    Query(request): Query<PreSignedUrlRequest>,
+) -> Result<Response, AppError> {
+    let content_type = request.content_type.trim().to_lowercase();
+    if !app_state.config.media_allowed_types.contains(&content_type) {
+        return Err(AppError::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("Content type {} is not allowed", content_type),
+        ));
+    }
+
+    if request.size == 0 || request.size > app_state.config.media_max_upload_bytes {
+        return Err(AppError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "File size must be between 1 and {} bytes",
+                app_state.config.media_max_upload_bytes
+            ),
+        ));
+    }
+
+    // a failed presign must not count against the quota
+    if let Err(retry_after) = app_state.upload_quota.peek(&user_info.id) {
+        tracing::warn!("upload quota exceeded | user {}", user_info.id);
+        return Ok(too_many_requests(retry_after));
+    }
+
     let path = format!("/media/{}", Uuid::new_v4());
 
+    // signed headers pin the upload to the declared type and exact size
+    let mut signed_headers = HeaderMap::new();
+    signed_headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_str(&content_type).map_err(map_to_app_error)?,
+    );
+    signed_headers.insert(CONTENT_LENGTH, HeaderValue::from(request.size));
+
     let presigned_uri = app_state
         .public_bucket
-        .presign_put(&path, 60, None, None)
+        .presign_put(&path, PRESIGNED_URL_EXPIRY_SECS, Some(signed_headers), None)
         .await
         .map_err(map_to_app_error)?;
+    app_state.upload_quota.record(&user_info.id);
+
+    // the upload itself goes straight to the bucket, this is the last point we see it
+    AuditEvent::new(AuditAction::Created)
+        .actor(Uuid::from_str(&user_info.id).unwrap())
+        .ip(client_ip)
+        .target(AuditTarget::Media, &path)
+        .changes(json!({
+            "content_type": { "from": null, "to": content_type },
+            "size": { "from": null, "to": request.size },
+        }))
+        .record(&app_state.db)
+        .await;
 
     Ok(Json(PreSignedUrlResponse {
         url: presigned_uri,
         path,
-    }))
+        headers: HashMap::from([
+            ("Content-Type".to_string(), content_type),
+            ("Content-Length".to_string(), request.size.to_string()),
+        ]),
+    })
+    .into_response())
 }
@@ -1,5 +1,15 @@
+use std::collections::HashMap;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PreSignedUrlRequest {
+    pub content_type: String,
+    pub size: u64,
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct PreSignedUrlResponse {
     pub url: String,
     pub path: String,
+    /// Headers the upload request must send unchanged, they are part of the signature.
+    pub headers: HashMap<String, String>,
 }
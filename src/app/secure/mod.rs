@@ -1,4 +1,10 @@
+pub mod audit;
 pub mod categories;
+pub mod invitations;
 pub mod media;
 pub mod posts;
+pub mod revisions;
+pub mod security;
+pub mod sessions;
+pub mod tokens;
 pub mod user;
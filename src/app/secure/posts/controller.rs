@@ -1,29 +1,39 @@
+use std::net::IpAddr;
 use std::str::FromStr;
 use std::sync::Arc;
 
 use axum::body::Body;
+use axum::extract::rejection::JsonRejection;
 use axum::extract::{FromRequest, Path, Request, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::{async_trait, Extension, Json, RequestExt};
-use sea_orm::prelude::Uuid;
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
 use sea_orm::sea_query::OnConflict;
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, EntityTrait, ModelTrait, QueryFilter,
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, EntityTrait,
+    LoaderTrait, ModelTrait, QueryFilter, QueryOrder, TransactionTrait,
 };
 
-use crate::app::auth::model::UserInfo;
+use crate::app::auth::model::{Permission, UserInfo};
+use crate::app::common::audit::{diff, AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::ClientIp;
 use crate::app::common::core::AppState;
 use crate::app::common::errors::AppError;
+use crate::app::orm::post_categories::Entity as PostCategories;
 use crate::app::orm::post_tags::{
     ActiveModel as PostTagActiveModel, Column as PostTagColumn, Entity as PostTags,
 };
-use crate::app::orm::posts::{ActiveModel as PostActiveModel, Entity as Posts, Model as Post};
+use crate::app::orm::posts::{
+    ActiveModel as PostActiveModel, Column as PostColumn, Entity as Posts, Model as Post,
+};
 use crate::app::orm::tags::{
     ActiveModel as TagActiveModel, Column as TagColumn, Entity as Tags, Model as Tag,
 };
+use crate::app::secure::revisions::service::record_revision;
 
-use super::model::{ModifyPostRequest, PostResponse};
+use super::model::{ModifyPostRequest, PatchPostRequest, PostResponse};
+use super::service::purge_posts;
 
 const POST_NOT_FOUND_ERROR: &str = "Post not found";
 
@@ -41,20 +51,11 @@ where
 }
 
 pub async fn get_post(
+    Extension(user_info): Extension<UserInfo>,
     State(app_state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<PostResponse>, AppError> {
-    let post = Posts::find_by_id(id).one(&app_state.db).await?;
-
-    let post = match post {
-        Some(post) => post,
-        None => {
-            return Err(AppError {
-                status: StatusCode::NOT_FOUND,
-                message: POST_NOT_FOUND_ERROR.to_string(),
-            });
-        }
-    };
+    let post = find_accessible_post(&user_info, &app_state, id).await?;
 
     let tags = post.find_related(Tags).all(&app_state.db).await?;
     Ok(Json(PostResponse::new(post, tags)))
@@ -63,18 +64,35 @@ pub async fn get_post(
 pub async fn create_post(
     Extension(user_info): Extension<UserInfo>,
     State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
     Json(payload): Json<ModifyPostRequest>,
 ) -> Result<Json<Post>, AppError> {
-    let post = create_new_post(&user_info, &app_state, &payload).await?;
-    let tags = insert_tags(&app_state, &payload).await?;
-    associate_tags_with_post(&app_state, &post, &tags).await?;
+    let actor_id = Uuid::from_str(&user_info.id).unwrap();
+    validate_schedule(&user_info, payload.publish_at, payload.unpublish_at, None)?;
+
+    let txn = app_state.db.begin().await?;
+    let post = create_new_post(&txn, &user_info, &payload).await?;
+    let (tags, created_tags) = insert_tags(&txn, &payload.tags).await?;
+    associate_tags_with_post(&txn, &post, &tags).await?;
+    record_revision(&txn, &post, &tags, actor_id, None).await?;
+    txn.commit().await?;
+
+    audit_created_tags(&app_state, &created_tags, actor_id, client_ip).await;
+
+    AuditEvent::new(AuditAction::Created)
+        .actor(actor_id)
+        .ip(client_ip)
+        .target(AuditTarget::Post, post.id)
+        .changes(diff(None, Some(&PostResponse::new(post.clone(), tags))))
+        .record(&app_state.db)
+        .await;
 
     Ok(Json(post))
 }
 
 async fn create_new_post(
+    db: &impl ConnectionTrait,
     user_info: &UserInfo,
-    app_state: &Arc<AppState>,
     payload: &ModifyPostRequest,
 ) -> Result<Post, AppError> {
     let post = PostActiveModel {
@@ -86,28 +104,36 @@ async fn create_new_post(
         slug: ActiveValue::Set(payload.slug.clone()),
         read_time_millis: ActiveValue::Set(payload.read_time_millis),
         cover_image: ActiveValue::Set(payload.cover_image.clone()),
+        draft: ActiveValue::Set(true),
+        published_at: ActiveValue::Set(None),
+        publish_at: ActiveValue::Set(payload.publish_at),
+        unpublish_at: ActiveValue::Set(payload.unpublish_at),
         ..Default::default()
     };
 
-    post.insert(&app_state.db).await.map_err(|err| err.into())
+    post.insert(db).await.map_err(|err| err.into())
 }
 
 pub async fn update_post(
+    Extension(user_info): Extension<UserInfo>,
     State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
     Path(id): Path<Uuid>,
     Json(payload): Json<ModifyPostRequest>,
 ) -> Result<Json<Post>, AppError> {
-    let post = Posts::find_by_id(id).one(&app_state.db).await?;
+    let actor_id = Uuid::from_str(&user_info.id).unwrap();
 
-    let mut post: PostActiveModel = match post {
-        Some(post) => post.into(),
-        None => {
-            return Err(AppError {
-                status: StatusCode::NOT_FOUND,
-                message: POST_NOT_FOUND_ERROR.to_string(),
-            });
-        }
-    };
+    let existing = find_accessible_post(&user_info, &app_state, id).await?;
+    validate_schedule(
+        &user_info,
+        payload.publish_at,
+        payload.unpublish_at,
+        Some(&existing),
+    )?;
+    let existing_tags = existing.find_related(Tags).all(&app_state.db).await?;
+    let before = PostResponse::new(existing.clone(), existing_tags);
+
+    let mut post: PostActiveModel = existing.into();
 
     post.title = ActiveValue::Set(payload.title.clone());
     post.content = ActiveValue::Set(payload.content.clone());
@@ -115,21 +141,437 @@ pub async fn update_post(
     post.category = ActiveValue::Set(Uuid::from_str(&payload.category).unwrap());
     post.read_time_millis = ActiveValue::Set(payload.read_time_millis);
     post.cover_image = ActiveValue::Set(payload.cover_image.clone());
+    post.publish_at = ActiveValue::Set(payload.publish_at);
+    post.unpublish_at = ActiveValue::Set(payload.unpublish_at);
 
-    let updates_post = post.update(&app_state.db).await?;
+    let txn = app_state.db.begin().await?;
+    let updates_post = post.update(&txn).await?;
 
     // remove and add tags
-    let tags = insert_tags(&app_state, &payload).await?;
-    associate_tags_with_post(&app_state, &updates_post, &tags).await?;
+    let (tags, created_tags) = insert_tags(&txn, &payload.tags).await?;
+    associate_tags_with_post(&txn, &updates_post, &tags).await?;
+    record_revision(&txn, &updates_post, &tags, actor_id, None).await?;
+    txn.commit().await?;
+
+    audit_created_tags(&app_state, &created_tags, actor_id, client_ip).await;
+
+    let after = PostResponse::new(updates_post.clone(), tags);
+    AuditEvent::new(AuditAction::Updated)
+        .actor(actor_id)
+        .ip(client_ip)
+        .target(AuditTarget::Post, updates_post.id)
+        .changes(diff(Some(&before), Some(&after)))
+        .record(&app_state.db)
+        .await;
 
     Ok(Json(updates_post))
 }
 
-async fn insert_tags(
+/// Changes only the fields present in the payload. Tags are replaced only
+/// when `tags` is sent.
+pub async fn patch_post(
+    Extension(user_info): Extension<UserInfo>,
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<Uuid>,
+    payload_r: Result<Json<PatchPostRequest>, JsonRejection>,
+) -> Result<Json<PostResponse>, AppError> {
+    // callers without access to the post learn nothing about their payload
+    let actor_id = Uuid::from_str(&user_info.id).unwrap();
+    let existing = find_accessible_post(&user_info, &app_state, id).await?;
+
+    let Json(payload) = payload_r.map_err(|rejection| {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid payload: {}", rejection.body_text()),
+        )
+    })?;
+
+    let mut errors = payload.field_errors();
+    if let Some(category) = payload.category {
+        if PostCategories::find_by_id(category)
+            .one(&app_state.db)
+            .await?
+            .is_none()
+        {
+            errors.push("category: does not exist".to_string());
+        }
+    }
+    if !errors.is_empty() {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, errors.join("; ")));
+    }
+
+    validate_schedule(
+        &user_info,
+        payload.publish_at.unwrap_or(existing.publish_at),
+        payload.unpublish_at.unwrap_or(existing.unpublish_at),
+        Some(&existing),
+    )?;
+
+    let existing_tags = existing.find_related(Tags).all(&app_state.db).await?;
+    let before = PostResponse::new(existing.clone(), existing_tags.clone());
+
+    let mut post: PostActiveModel = existing.clone().into();
+    if let Some(title) = payload.title {
+        post.title = ActiveValue::Set(title);
+    }
+    if let Some(content) = payload.content {
+        post.content = ActiveValue::Set(content);
+    }
+    if let Some(summary) = payload.summary {
+        post.summary = ActiveValue::Set(summary);
+    }
+    if let Some(category) = payload.category {
+        post.category = ActiveValue::Set(category);
+    }
+    if let Some(slug) = payload.slug {
+        post.slug = ActiveValue::Set(slug);
+    }
+    if let Some(read_time_millis) = payload.read_time_millis {
+        post.read_time_millis = ActiveValue::Set(read_time_millis);
+    }
+    if let Some(cover_image) = payload.cover_image {
+        post.cover_image = ActiveValue::Set(cover_image);
+    }
+    if let Some(publish_at) = payload.publish_at {
+        post.publish_at = ActiveValue::Set(publish_at);
+    }
+    if let Some(unpublish_at) = payload.unpublish_at {
+        post.unpublish_at = ActiveValue::Set(unpublish_at);
+    }
+
+    let txn = app_state.db.begin().await?;
+    let patched = if post.is_changed() {
+        post.update(&txn).await?
+    } else {
+        existing
+    };
+
+    let (tags, created_tags) = match &payload.tags {
+        Some(names) => {
+            let (tags, created_tags) = insert_tags(&txn, names).await?;
+            associate_tags_with_post(&txn, &patched, &tags).await?;
+            (tags, created_tags)
+        }
+        None => (existing_tags, Vec::new()),
+    };
+
+    let after = PostResponse::new(patched.clone(), tags.clone());
+    let changes = diff(Some(&before), Some(&after));
+    let changed = changes
+        .as_object()
+        .is_some_and(|changes| !changes.is_empty());
+    if changed {
+        record_revision(&txn, &patched, &tags, actor_id, None).await?;
+    }
+    txn.commit().await?;
+
+    audit_created_tags(&app_state, &created_tags, actor_id, client_ip).await;
+    if !changed {
+        return Ok(Json(after));
+    }
+
+    AuditEvent::new(AuditAction::Updated)
+        .actor(actor_id)
+        .ip(client_ip)
+        .target(AuditTarget::Post, patched.id)
+        .changes(changes)
+        .record(&app_state.db)
+        .await;
+
+    Ok(Json(after))
+}
+
+/// Makes the post visible on the public endpoints right away, replacing any
+/// `publish_at`. Publishing a post that is already published keeps its
+/// original `published_at`.
+pub async fn publish_post(
+    Extension(user_info): Extension<UserInfo>,
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PostResponse>, AppError> {
+    let post = find_accessible_post(&user_info, &app_state, id).await?;
+    let published_at = post
+        .published_at
+        .unwrap_or_else(|| chrono::Utc::now().into());
+
+    set_publication(&user_info, &app_state, client_ip, post, Some(published_at)).await
+}
+
+/// Turns the post back into a draft and hides it from the public endpoints.
+/// Clears its schedule as well.
+pub async fn unpublish_post(
+    Extension(user_info): Extension<UserInfo>,
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PostResponse>, AppError> {
+    let post = find_accessible_post(&user_info, &app_state, id).await?;
+
+    set_publication(&user_info, &app_state, client_ip, post, None).await
+}
+
+async fn set_publication(
+    user_info: &UserInfo,
     app_state: &Arc<AppState>,
-    payload: &ModifyPostRequest,
-) -> Result<Vec<Tag>, AppError> {
-    let tag_models = payload.tags.iter().map(|tag| TagActiveModel {
+    client_ip: IpAddr,
+    post: Post,
+    published_at: Option<DateTimeWithTimeZone>,
+) -> Result<Json<PostResponse>, AppError> {
+    let tags = post.find_related(Tags).all(&app_state.db).await?;
+    let unpublish_at = published_at.and(post.unpublish_at);
+    if post.published_at == published_at
+        && post.publish_at.is_none()
+        && post.unpublish_at == unpublish_at
+    {
+        return Ok(Json(PostResponse::new(post, tags)));
+    }
+
+    let before = PostResponse::new(post.clone(), tags.clone());
+
+    let mut active: PostActiveModel = post.into();
+    active.draft = ActiveValue::Set(published_at.is_none());
+    active.published_at = ActiveValue::Set(published_at);
+    active.publish_at = ActiveValue::Set(None);
+    active.unpublish_at = ActiveValue::Set(unpublish_at);
+    let post = active.update(&app_state.db).await?;
+
+    let after = PostResponse::new(post.clone(), tags);
+    AuditEvent::new(AuditAction::Updated)
+        .actor(Uuid::from_str(&user_info.id).unwrap())
+        .ip(client_ip)
+        .target(AuditTarget::Post, post.id)
+        .changes(diff(Some(&before), Some(&after)))
+        .record(&app_state.db)
+        .await;
+
+    Ok(Json(after))
+}
+
+/// Scheduling is publishing ahead of time, so changing `publish_at` or
+/// `unpublish_at` needs `posts:publish`. Payloads that repeat the current
+/// schedule are fine without it.
+fn validate_schedule(
+    user_info: &UserInfo,
+    publish_at: Option<DateTimeWithTimeZone>,
+    unpublish_at: Option<DateTimeWithTimeZone>,
+    existing: Option<&Post>,
+) -> Result<(), AppError> {
+    let current = existing.map_or((None, None), |post| (post.publish_at, post.unpublish_at));
+    if current != (publish_at, unpublish_at) && !user_info.has_permission(Permission::PostsPublish)
+    {
+        return Err(AppError::forbidden());
+    }
+
+    if publish_at.is_some() && existing.is_some_and(|post| post.published_at.is_some()) {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "Post is already published".to_string(),
+        ));
+    }
+
+    if let (Some(publish_at), Some(unpublish_at)) = (publish_at, unpublish_at) {
+        if unpublish_at <= publish_at {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                "unpublish_at must be after publish_at".to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves the post to the trash. It disappears from the public endpoints and
+/// the other admin endpoints until restored, and is purged for good after the
+/// configured retention.
+pub async fn delete_post(
+    Extension(user_info): Extension<UserInfo>,
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    let post = find_accessible_post(&user_info, &app_state, id).await?;
+    let tags = post.find_related(Tags).all(&app_state.db).await?;
+    let before = PostResponse::new(post.clone(), tags.clone());
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    let mut active: PostActiveModel = post.into();
+    active.deleted_at = ActiveValue::Set(Some(now));
+    let post = active.update(&app_state.db).await?;
+
+    AuditEvent::new(AuditAction::Deleted)
+        .actor(Uuid::from_str(&user_info.id).unwrap())
+        .ip(client_ip)
+        .target(AuditTarget::Post, post.id)
+        .changes(diff(Some(&before), Some(&PostResponse::new(post, tags))))
+        .record(&app_state.db)
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Trashed posts the caller may restore or purge, most recently deleted first.
+pub async fn get_trash(
+    Extension(user_info): Extension<UserInfo>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<PostResponse>>, AppError> {
+    let mut select = Posts::find().filter(PostColumn::DeletedAt.is_not_null());
+    if !user_info.has_permission(Permission::PostsWriteOthers) {
+        select = select.filter(PostColumn::Author.eq(Uuid::from_str(&user_info.id).unwrap()));
+    }
+
+    let posts = select
+        .order_by_desc(PostColumn::DeletedAt)
+        .all(&app_state.db)
+        .await?;
+    let tags = posts
+        .load_many_to_many(Tags, PostTags, &app_state.db)
+        .await?;
+
+    Ok(Json(
+        posts
+            .into_iter()
+            .zip(tags)
+            .map(|(post, tags)| PostResponse::new(post, tags))
+            .collect(),
+    ))
+}
+
+/// Takes the post out of the trash with the status it had before.
+pub async fn restore_post(
+    Extension(user_info): Extension<UserInfo>,
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PostResponse>, AppError> {
+    let post = find_accessible_trashed_post(&user_info, &app_state, id).await?;
+    let tags = post.find_related(Tags).all(&app_state.db).await?;
+    let before = PostResponse::new(post.clone(), tags.clone());
+
+    let mut active: PostActiveModel = post.into();
+    active.deleted_at = ActiveValue::Set(None);
+    let post = active.update(&app_state.db).await?;
+
+    let after = PostResponse::new(post, tags);
+    AuditEvent::new(AuditAction::Updated)
+        .actor(Uuid::from_str(&user_info.id).unwrap())
+        .ip(client_ip)
+        .target(AuditTarget::Post, after.id)
+        .changes(diff(Some(&before), Some(&after)))
+        .record(&app_state.db)
+        .await;
+
+    Ok(Json(after))
+}
+
+/// Deletes a trashed post for good, with its tags, comments and revisions.
+pub async fn purge_post(
+    Extension(user_info): Extension<UserInfo>,
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    let post = find_accessible_trashed_post(&user_info, &app_state, id).await?;
+    let tags = post.find_related(Tags).all(&app_state.db).await?;
+
+    let txn = app_state.db.begin().await?;
+    purge_posts(&txn, &[post.id]).await?;
+    txn.commit().await?;
+
+    AuditEvent::new(AuditAction::Deleted)
+        .actor(Uuid::from_str(&user_info.id).unwrap())
+        .ip(client_ip)
+        .target(AuditTarget::Post, post.id)
+        .changes(diff(Some(&PostResponse::new(post, tags)), None))
+        .record(&app_state.db)
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Loads a post the caller is allowed to work on. Authors only get their own
+/// posts, roles with `posts:write_others` get any. Every handler that reads or
+/// modifies a single post should go through this. Trashed posts are not found.
+pub async fn find_accessible_post(
+    user_info: &UserInfo,
+    app_state: &Arc<AppState>,
+    id: Uuid,
+) -> Result<Post, AppError> {
+    load_accessible_post(user_info, app_state, id, false).await
+}
+
+/// Like [`find_accessible_post`], but only finds posts in the trash.
+async fn find_accessible_trashed_post(
+    user_info: &UserInfo,
+    app_state: &Arc<AppState>,
+    id: Uuid,
+) -> Result<Post, AppError> {
+    load_accessible_post(user_info, app_state, id, true).await
+}
+
+async fn load_accessible_post(
+    user_info: &UserInfo,
+    app_state: &Arc<AppState>,
+    id: Uuid,
+    trashed: bool,
+) -> Result<Post, AppError> {
+    let deleted_at = if trashed {
+        PostColumn::DeletedAt.is_not_null()
+    } else {
+        PostColumn::DeletedAt.is_null()
+    };
+    let post = Posts::find_by_id(id)
+        .filter(deleted_at)
+        .one(&app_state.db)
+        .await?;
+
+    let post = match post {
+        Some(post) => post,
+        None => {
+            return Err(AppError {
+                status: StatusCode::NOT_FOUND,
+                message: POST_NOT_FOUND_ERROR.to_string(),
+            });
+        }
+    };
+
+    let is_author = post.author.to_string() == user_info.id;
+    if !is_author && !user_info.has_permission(Permission::PostsWriteOthers) {
+        tracing::warn!(
+            "post access denied | user {} | post {} | author {}",
+            user_info.id,
+            post.id,
+            post.author
+        );
+        return Err(AppError::forbidden());
+    }
+
+    Ok(post)
+}
+
+/// Creates the tags that do not exist yet. Returns all tags named and, for
+/// [`audit_created_tags`] once the caller has committed, the new ones.
+pub async fn insert_tags(
+    db: &impl ConnectionTrait,
+    names: &[String],
+) -> Result<(Vec<Tag>, Vec<Tag>), AppError> {
+    let existing = Tags::find()
+        .filter(TagColumn::Name.is_in(names.to_vec()))
+        .all(db)
+        .await?;
+
+    let missing = names
+        .iter()
+        .filter(|tag| !existing.iter().any(|existing| existing.name == **tag))
+        .collect::<Vec<&String>>();
+
+    if missing.is_empty() {
+        return Ok((existing, Vec::new()));
+    }
+
+    let tag_models = missing.iter().map(|tag| TagActiveModel {
         name: ActiveValue::Set(tag.to_string()),
         ..Default::default()
     });
@@ -137,19 +579,42 @@ async fn insert_tags(
     Tags::insert_many(tag_models)
         .on_conflict(OnConflict::column(TagColumn::Name).do_nothing().to_owned())
         .do_nothing()
-        .exec(&app_state.db)
+        .exec(db)
         .await?;
 
     let tags = Tags::find()
-        .filter(TagColumn::Name.is_in(payload.tags.clone()))
-        .all(&app_state.db)
+        .filter(TagColumn::Name.is_in(names.to_vec()))
+        .all(db)
         .await?;
 
-    Ok(tags)
+    let created = tags
+        .iter()
+        .filter(|tag| !existing.contains(tag))
+        .cloned()
+        .collect();
+
+    Ok((tags, created))
 }
 
-async fn associate_tags_with_post(
-    app_state: &Arc<AppState>,
+pub async fn audit_created_tags(
+    app_state: &AppState,
+    tags: &[Tag],
+    actor_id: Uuid,
+    client_ip: IpAddr,
+) {
+    for tag in tags {
+        AuditEvent::new(AuditAction::Created)
+            .actor(actor_id)
+            .ip(client_ip)
+            .target(AuditTarget::Tag, tag.id)
+            .changes(diff(None, Some(tag)))
+            .record(&app_state.db)
+            .await;
+    }
+}
+
+pub async fn associate_tags_with_post(
+    db: &impl ConnectionTrait,
     post: &Post,
     tags: &[Tag],
 ) -> Result<(), AppError> {
@@ -169,7 +634,7 @@ async fn associate_tags_with_post(
                     .is_not_in(tags.iter().map(|tag| tag.id as Uuid).collect::<Vec<Uuid>>()),
             ),
         )
-        .exec(&app_state.db)
+        .exec(db)
         .await?;
 
     PostTags::insert_many(post_tags)
@@ -179,8 +644,129 @@ async fn associate_tags_with_post(
                 .to_owned(),
         )
         .do_nothing()
-        .exec(&app_state.db)
+        .exec(db)
         .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::app::auth::model::{AuthMethod, Role};
+
+    use super::*;
+
+    fn user(role: Role) -> UserInfo {
+        UserInfo::for_tests(role, AuthMethod::Cookie)
+    }
+
+    fn post(published: bool) -> Post {
+        let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+        Post {
+            id: Uuid::new_v4(),
+            title: "Title".to_string(),
+            summary: "Summary".to_string(),
+            content: "Content".to_string(),
+            category: Uuid::new_v4(),
+            slug: "title".to_string(),
+            author: Uuid::new_v4(),
+            draft: !published,
+            created_at: now,
+            updated_at: None,
+            published_at: published.then_some(now),
+            publish_at: None,
+            unpublish_at: None,
+            deleted_at: None,
+            read_time_millis: 0,
+            cover_image: String::new(),
+        }
+    }
+
+    fn at(hours: i64) -> Option<DateTimeWithTimeZone> {
+        Some((chrono::Utc::now() + chrono::Duration::hours(hours)).into())
+    }
+
+    fn status(result: Result<(), AppError>) -> Option<StatusCode> {
+        result.err().map(|err| err.status)
+    }
+
+    #[test]
+    fn scheduling_needs_the_publish_permission() {
+        let contributor = user(Role::Contributor);
+        assert_eq!(
+            status(validate_schedule(&contributor, at(1), None, None)),
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            status(validate_schedule(&contributor, None, None, None)),
+            None
+        );
+        assert_eq!(
+            status(validate_schedule(&user(Role::Author), at(1), at(2), None)),
+            None
+        );
+    }
+
+    #[test]
+    fn repeating_the_current_schedule_needs_no_permission() {
+        let existing = Post {
+            publish_at: at(1),
+            ..post(false)
+        };
+
+        assert_eq!(
+            status(validate_schedule(
+                &user(Role::Contributor),
+                existing.publish_at,
+                None,
+                Some(&existing)
+            )),
+            None
+        );
+        assert_eq!(
+            status(validate_schedule(
+                &user(Role::Contributor),
+                None,
+                None,
+                Some(&existing)
+            )),
+            Some(StatusCode::FORBIDDEN)
+        );
+    }
+
+    #[test]
+    fn published_posts_cannot_be_scheduled_again() {
+        assert_eq!(
+            status(validate_schedule(
+                &user(Role::Editor),
+                at(1),
+                None,
+                Some(&post(true))
+            )),
+            Some(StatusCode::CONFLICT)
+        );
+        assert_eq!(
+            status(validate_schedule(
+                &user(Role::Editor),
+                None,
+                at(1),
+                Some(&post(true))
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn unpublish_at_has_to_follow_publish_at() {
+        let editor = user(Role::Editor);
+        assert_eq!(
+            status(validate_schedule(&editor, at(2), at(1), None)),
+            Some(StatusCode::BAD_REQUEST)
+        );
+        let same = at(1);
+        assert_eq!(
+            status(validate_schedule(&editor, same, same, None)),
+            Some(StatusCode::BAD_REQUEST)
+        );
+    }
+}
@@ -1,2 +1,4 @@
 pub mod controller;
 pub mod model;
+pub mod scheduler;
+pub mod service;
@@ -1,6 +1,7 @@
 use crate::app::orm::posts::Model as Post;
 use crate::app::orm::tags::Model as Tag;
 use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use serde::{Deserialize, Deserializer};
 
 #[derive(Debug, serde::Serialize)]
 pub struct PostResponse {
@@ -12,7 +13,11 @@ pub struct PostResponse {
     pub slug: String,
     pub author: Uuid,
     pub cover_image: String,
-    pub draft: Option<bool>,
+    pub status: PostStatus,
+    pub published_at: Option<DateTimeWithTimeZone>,
+    pub publish_at: Option<DateTimeWithTimeZone>,
+    pub unpublish_at: Option<DateTimeWithTimeZone>,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: Option<DateTimeWithTimeZone>,
     pub read_time_millis: i64,
@@ -21,6 +26,9 @@ pub struct PostResponse {
 
 impl PostResponse {
     pub fn new(post: Post, tags: Vec<Tag>) -> Self {
+        let status = PostStatus::of(&post);
+        let mut tags = tags.into_iter().map(|tag| tag.name).collect::<Vec<_>>();
+        tags.sort();
         PostResponse {
             id: post.id,
             title: post.title,
@@ -29,12 +37,50 @@ impl PostResponse {
             category: post.category,
             slug: post.slug,
             author: post.author,
-            draft: post.draft,
+            status,
+            published_at: post.published_at,
+            publish_at: post.publish_at,
+            unpublish_at: post.unpublish_at,
+            deleted_at: post.deleted_at,
             cover_image: post.cover_image,
             created_at: post.created_at,
             updated_at: post.updated_at,
             read_time_millis: post.read_time_millis,
-            tags: tags.into_iter().map(|tag| tag.name).collect(),
+            tags,
+        }
+    }
+}
+
+/// Only published posts are visible on the public endpoints. A scheduled
+/// post is a draft with a `publish_at` in the future, a trashed post one that
+/// was deleted but not purged yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostStatus {
+    Draft,
+    Scheduled,
+    Published,
+    Trashed,
+}
+
+impl PostStatus {
+    /// Follows `publish_at` and `unpublish_at` even when the scheduler has not
+    /// applied them yet, like the public endpoints do.
+    pub fn of(post: &Post) -> Self {
+        let now = chrono::Utc::now();
+        let expired = post.unpublish_at.is_some_and(|at| at <= now);
+        let due = post.publish_at.is_some_and(|at| at <= now);
+
+        if post.deleted_at.is_some() {
+            PostStatus::Trashed
+        } else if expired {
+            PostStatus::Draft
+        } else if post.published_at.is_some() || due {
+            PostStatus::Published
+        } else if post.publish_at.is_some() {
+            PostStatus::Scheduled
+        } else {
+            PostStatus::Draft
         }
     }
 }
@@ -49,4 +95,233 @@ pub struct ModifyPostRequest {
     pub slug: String,
     pub read_time_millis: i64,
     pub cover_image: String,
+    /// When the draft goes live, needs `posts:publish`.
+    #[serde(default)]
+    pub publish_at: Option<DateTimeWithTimeZone>,
+    /// When the post turns back into a draft, needs `posts:publish`.
+    #[serde(default)]
+    pub unpublish_at: Option<DateTimeWithTimeZone>,
+}
+
+/// Body of `PATCH /admin/posts/:id`. Absent fields are left as they are;
+/// `publish_at` and `unpublish_at` can also be `null` to clear them.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatchPostRequest {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub summary: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub category: Option<Uuid>,
+    pub slug: Option<String>,
+    pub read_time_millis: Option<i64>,
+    pub cover_image: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub publish_at: Option<Option<DateTimeWithTimeZone>>,
+    #[serde(default, deserialize_with = "present")]
+    pub unpublish_at: Option<Option<DateTimeWithTimeZone>>,
+}
+
+impl PatchPostRequest {
+    /// Checks every present field on its own and describes each problem as
+    /// `field: reason`.
+    pub fn field_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for (field, value) in [
+            ("title", &self.title),
+            ("summary", &self.summary),
+            ("content", &self.content),
+        ] {
+            if value
+                .as_deref()
+                .is_some_and(|value| value.trim().is_empty())
+            {
+                errors.push(format!("{}: must not be empty", field));
+            }
+        }
+
+        if let Some(slug) = &self.slug {
+            let valid = !slug.is_empty()
+                && !slug.starts_with('-')
+                && !slug.ends_with('-')
+                && slug
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            if !valid {
+                errors.push(
+                    "slug: may only contain lowercase letters, digits and inner hyphens"
+                        .to_string(),
+                );
+            }
+        }
+
+        if self.read_time_millis.is_some_and(|millis| millis < 0) {
+            errors.push("read_time_millis: must not be negative".to_string());
+        }
+
+        if let Some(tags) = &self.tags {
+            if tags.iter().any(|tag| tag.trim().is_empty()) {
+                errors.push("tags: must not contain empty names".to_string());
+            }
+        }
+
+        errors
+    }
+}
+
+/// Tells a field sent as `null` (`Some(None)`) apart from one left out (`None`).
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn post() -> Post {
+        Post {
+            id: Uuid::new_v4(),
+            title: "Title".to_string(),
+            summary: "Summary".to_string(),
+            content: "Content".to_string(),
+            category: Uuid::new_v4(),
+            slug: "title".to_string(),
+            author: Uuid::new_v4(),
+            draft: true,
+            created_at: chrono::Utc::now().into(),
+            updated_at: None,
+            published_at: None,
+            publish_at: None,
+            unpublish_at: None,
+            deleted_at: None,
+            read_time_millis: 0,
+            cover_image: String::new(),
+        }
+    }
+
+    fn hours_from_now(hours: i64) -> Option<DateTimeWithTimeZone> {
+        Some((chrono::Utc::now() + chrono::Duration::hours(hours)).into())
+    }
+
+    #[test]
+    fn status_follows_the_schedule() {
+        assert_eq!(PostStatus::of(&post()), PostStatus::Draft);
+
+        let scheduled = Post {
+            publish_at: hours_from_now(1),
+            ..post()
+        };
+        assert_eq!(PostStatus::of(&scheduled), PostStatus::Scheduled);
+
+        // due but not picked up by the scheduler yet
+        let due = Post {
+            publish_at: hours_from_now(-1),
+            unpublish_at: hours_from_now(1),
+            ..post()
+        };
+        assert_eq!(PostStatus::of(&due), PostStatus::Published);
+
+        let expired = Post {
+            draft: false,
+            published_at: hours_from_now(-2),
+            unpublish_at: hours_from_now(-1),
+            ..post()
+        };
+        assert_eq!(PostStatus::of(&expired), PostStatus::Draft);
+    }
+
+    #[test]
+    fn trashed_wins_over_every_other_status() {
+        let trashed = Post {
+            draft: false,
+            published_at: hours_from_now(-1),
+            deleted_at: hours_from_now(0),
+            ..post()
+        };
+        assert_eq!(PostStatus::of(&trashed), PostStatus::Trashed);
+        assert_eq!(
+            serde_json::to_value(PostStatus::of(&trashed)).unwrap(),
+            json!("trashed")
+        );
+    }
+
+    fn patch(body: serde_json::Value) -> Result<PatchPostRequest, serde_json::Error> {
+        serde_json::from_value(body)
+    }
+
+    #[test]
+    fn patch_tells_null_apart_from_absent() {
+        let absent = patch(json!({ "title": "New title" })).unwrap();
+        assert_eq!(absent.title.as_deref(), Some("New title"));
+        assert!(absent.publish_at.is_none());
+        assert!(absent.unpublish_at.is_none());
+        assert!(absent.tags.is_none());
+
+        let cleared = patch(json!({ "publish_at": null, "unpublish_at": null })).unwrap();
+        assert_eq!(cleared.publish_at, Some(None));
+        assert_eq!(cleared.unpublish_at, Some(None));
+
+        let set = patch(json!({ "publish_at": "2030-01-01T10:00:00Z" })).unwrap();
+        assert_eq!(
+            set.publish_at.flatten().map(|at| at.timestamp()),
+            Some(1893492000)
+        );
+    }
+
+    #[test]
+    fn patch_rejects_unknown_and_mistyped_fields() {
+        assert!(patch(json!({ "titel": "typo" })).is_err());
+        assert!(patch(json!({ "draft": false })).is_err());
+        assert!(patch(json!({ "category": "not a uuid" })).is_err());
+        assert!(patch(json!({ "read_time_millis": "5" })).is_err());
+        assert!(patch(json!({ "publish_at": "tomorrow" })).is_err());
+    }
+
+    #[test]
+    fn empty_patch_has_no_errors() {
+        assert!(patch(json!({})).unwrap().field_errors().is_empty());
+    }
+
+    #[test]
+    fn field_errors_name_every_bad_field() {
+        let request = patch(json!({
+            "title": "  ",
+            "content": "",
+            "summary": "fine",
+            "slug": "Not A Slug",
+            "read_time_millis": -1,
+            "tags": ["rust", " "],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            request.field_errors(),
+            [
+                "title: must not be empty",
+                "content: must not be empty",
+                "slug: may only contain lowercase letters, digits and inner hyphens",
+                "read_time_millis: must not be negative",
+                "tags: must not contain empty names",
+            ]
+        );
+    }
+
+    #[test]
+    fn slugs_need_inner_hyphens_only() {
+        for slug in ["hello", "hello-world-2", "2024"] {
+            let request = patch(json!({ "slug": slug })).unwrap();
+            assert!(request.field_errors().is_empty(), "{}", slug);
+        }
+        for slug in ["", "-hello", "hello-", "hello_world", "héllo"] {
+            let request = patch(json!({ "slug": slug })).unwrap();
+            assert_eq!(request.field_errors().len(), 1, "{}", slug);
+        }
+    }
 }
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseTransaction, DbBackend, EntityTrait, QueryFilter,
+    Statement, TransactionTrait,
+};
+
+use crate::app::common::audit::{diff, AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::posts::{Column as PostColumn, Entity as Posts, Model as Post};
+
+use super::service::purge_posts;
+
+/// How often due schedules are applied. The public endpoints follow the
+/// schedule on their own, this only bounds how long the stored status lags.
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Key of the transaction level advisory lock that lets only one instance
+/// apply schedules at a time.
+const SCHEDULER_LOCK_KEY: i64 = 0x706f_7374_7363_6864;
+
+/// Publishes and unpublishes posts whose `publish_at` or `unpublish_at` has
+/// passed and purges posts that outlived the trash retention, for as long as
+/// the server runs. The schedule lives in the database, so anything that came
+/// due while no instance ran is applied on the first pass after a start.
+pub async fn run_post_scheduler(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(SCHEDULER_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = apply_due_schedules(&app_state).await {
+            tracing::error!("failed to apply post schedules | {}", err.message);
+        }
+    }
+}
+
+async fn apply_due_schedules(app_state: &AppState) -> Result<(), AppError> {
+    let txn = app_state.db.begin().await?;
+    if !try_lock_scheduler(&txn).await? {
+        return Ok(());
+    }
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    let published = publish_due_posts(&txn, now).await?;
+    let unpublished = unpublish_expired_posts(&txn, now).await?;
+    let purged = match app_state.config.post_trash_retention_days {
+        Some(days) => purge_expired_trash(&txn, now - chrono::Duration::days(days)).await?,
+        None => Vec::new(),
+    };
+    txn.commit().await?;
+
+    for (before, after) in published.iter().chain(unpublished.iter()) {
+        tracing::info!(
+            "post schedule applied | {} | draft {}",
+            after.id,
+            after.draft
+        );
+        AuditEvent::new(AuditAction::Updated)
+            .target(AuditTarget::Post, after.id)
+            .changes(diff(Some(before), Some(after)))
+            .record(&app_state.db)
+            .await;
+    }
+
+    for post in purged {
+        tracing::info!("trashed post purged | {}", post.id);
+        AuditEvent::new(AuditAction::Deleted)
+            .target(AuditTarget::Post, post.id)
+            .changes(diff(Some(&post), None))
+            .record(&app_state.db)
+            .await;
+    }
+
+    Ok(())
+}
+
+/// Held until the transaction ends. Another instance that is mid-pass makes
+/// this one skip its turn.
+async fn try_lock_scheduler(txn: &DatabaseTransaction) -> Result<bool, AppError> {
+    let row = txn
+        .query_one(Statement::from_string(
+            DbBackend::Postgres,
+            format!("SELECT pg_try_advisory_xact_lock({SCHEDULER_LOCK_KEY}) AS locked"),
+        ))
+        .await?;
+
+    match row {
+        Some(row) => Ok(row.try_get::<bool>("", "locked")?),
+        None => Ok(false),
+    }
+}
+
+async fn publish_due_posts(
+    txn: &DatabaseTransaction,
+    now: DateTimeWithTimeZone,
+) -> Result<Vec<(Post, Post)>, AppError> {
+    let due = Posts::find()
+        .filter(PostColumn::PublishAt.lte(now))
+        .filter(PostColumn::PublishedAt.is_null())
+        .filter(PostColumn::DeletedAt.is_null())
+        .all(txn)
+        .await?;
+    if due.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let updated = Posts::update_many()
+        .col_expr(PostColumn::Draft, Expr::value(false))
+        .col_expr(
+            PostColumn::PublishedAt,
+            Expr::col(PostColumn::PublishAt).into(),
+        )
+        .col_expr(
+            PostColumn::PublishAt,
+            Expr::value(Option::<DateTimeWithTimeZone>::None),
+        )
+        .filter(PostColumn::Id.is_in(due.iter().map(|post| post.id)))
+        .filter(PostColumn::PublishAt.lte(now))
+        .filter(PostColumn::PublishedAt.is_null())
+        .exec_with_returning(txn)
+        .await?;
+
+    Ok(pair_with_before(due, updated))
+}
+
+async fn unpublish_expired_posts(
+    txn: &DatabaseTransaction,
+    now: DateTimeWithTimeZone,
+) -> Result<Vec<(Post, Post)>, AppError> {
+    let expired = Posts::find()
+        .filter(PostColumn::UnpublishAt.lte(now))
+        .all(txn)
+        .await?;
+    if expired.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let updated = Posts::update_many()
+        .col_expr(PostColumn::Draft, Expr::value(true))
+        .col_expr(
+            PostColumn::PublishedAt,
+            Expr::value(Option::<DateTimeWithTimeZone>::None),
+        )
+        .col_expr(
+            PostColumn::UnpublishAt,
+            Expr::value(Option::<DateTimeWithTimeZone>::None),
+        )
+        .filter(PostColumn::Id.is_in(expired.iter().map(|post| post.id)))
+        .filter(PostColumn::UnpublishAt.lte(now))
+        .exec_with_returning(txn)
+        .await?;
+
+    Ok(pair_with_before(expired, updated))
+}
+
+async fn purge_expired_trash(
+    txn: &DatabaseTransaction,
+    deleted_before: DateTimeWithTimeZone,
+) -> Result<Vec<Post>, AppError> {
+    let expired = Posts::find()
+        .filter(PostColumn::DeletedAt.lte(deleted_before))
+        .all(txn)
+        .await?;
+
+    let ids = expired.iter().map(|post| post.id).collect::<Vec<Uuid>>();
+    purge_posts(txn, &ids).await?;
+
+    Ok(expired)
+}
+
+fn pair_with_before(before: Vec<Post>, after: Vec<Post>) -> Vec<(Post, Post)> {
+    let mut before: HashMap<Uuid, Post> = before.into_iter().map(|post| (post.id, post)).collect();
+
+    after
+        .into_iter()
+        .filter_map(|post| before.remove(&post.id).map(|old| (old, post)))
+        .collect()
+}
@@ -0,0 +1,33 @@
+use sea_orm::prelude::Uuid;
+use sea_orm::{ColumnTrait, DatabaseTransaction, EntityTrait, QueryFilter};
+
+use crate::app::common::errors::AppError;
+use crate::app::orm::post_comments::{Column as PostCommentColumn, Entity as PostComments};
+use crate::app::orm::post_tags::{Column as PostTagColumn, Entity as PostTags};
+use crate::app::orm::posts::{Column as PostColumn, Entity as Posts};
+
+/// Deletes the posts for good, together with their tags and comments.
+/// Revisions go with them through their foreign key. Runs in the caller's
+/// transaction so a failure leaves nothing half deleted.
+pub async fn purge_posts(txn: &DatabaseTransaction, ids: &[Uuid]) -> Result<(), AppError> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    PostTags::delete_many()
+        .filter(PostTagColumn::PostId.is_in(ids.to_vec()))
+        .exec(txn)
+        .await?;
+
+    PostComments::delete_many()
+        .filter(PostCommentColumn::PostId.is_in(ids.to_vec()))
+        .exec(txn)
+        .await?;
+
+    Posts::delete_many()
+        .filter(PostColumn::Id.is_in(ids.to_vec()))
+        .exec(txn)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use sea_orm::prelude::Uuid;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, ModelTrait, QueryFilter, QueryOrder,
+    TransactionTrait,
+};
+
+use crate::app::auth::model::UserInfo;
+use crate::app::common::audit::{diff, AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::ClientIp;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::post_revisions::{
+    Column as PostRevisionColumn, Entity as PostRevisions, Model as PostRevision,
+};
+use crate::app::orm::posts::ActiveModel as PostActiveModel;
+use crate::app::orm::tags::Entity as Tags;
+use crate::app::secure::posts::controller::{
+    associate_tags_with_post, audit_created_tags, find_accessible_post, insert_tags,
+};
+use crate::app::secure::posts::model::PostResponse;
+
+use super::model::{RevisionDiffQuery, RevisionDiffResponse, RevisionResponse};
+use super::service::{diff_revisions, record_revision, revision_tags};
+
+const REVISION_NOT_FOUND_ERROR: &str = "Revision not found";
+
+/// Newest revision first.
+pub async fn get_revisions(
+    Extension(user_info): Extension<UserInfo>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<RevisionResponse>>, AppError> {
+    let post = find_accessible_post(&user_info, &app_state, id).await?;
+
+    let revisions = PostRevisions::find()
+        .filter(PostRevisionColumn::PostId.eq(post.id))
+        .order_by_desc(PostRevisionColumn::Revision)
+        .all(&app_state.db)
+        .await?;
+
+    Ok(Json(
+        revisions.into_iter().map(RevisionResponse::new).collect(),
+    ))
+}
+
+pub async fn get_revision_diff(
+    Extension(user_info): Extension<UserInfo>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<RevisionDiffQuery>,
+) -> Result<Json<RevisionDiffResponse>, AppError> {
+    let post = find_accessible_post(&user_info, &app_state, id).await?;
+
+    let from = find_revision(&app_state, post.id, query.from).await?;
+    let to = find_revision(&app_state, post.id, query.to).await?;
+
+    Ok(Json(RevisionDiffResponse {
+        from: from.revision,
+        to: to.revision,
+        fields: diff_revisions(&from, &to),
+    }))
+}
+
+/// Puts the fields of an old revision back on the post. The result is saved
+/// as a new revision, so the history before the restore stays intact.
+pub async fn restore_revision(
+    Extension(user_info): Extension<UserInfo>,
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(client_ip): ClientIp,
+    Path((id, revision)): Path<(Uuid, i32)>,
+) -> Result<Json<PostResponse>, AppError> {
+    let actor_id = Uuid::from_str(&user_info.id).unwrap();
+
+    let existing = find_accessible_post(&user_info, &app_state, id).await?;
+    let revision = find_revision(&app_state, existing.id, revision).await?;
+    let existing_tags = existing.find_related(Tags).all(&app_state.db).await?;
+    let before = PostResponse::new(existing.clone(), existing_tags);
+
+    let mut post: PostActiveModel = existing.into();
+    post.title = ActiveValue::Set(revision.title.clone());
+    post.slug = ActiveValue::Set(revision.slug.clone());
+    post.content = ActiveValue::Set(revision.content.clone());
+    post.summary = ActiveValue::Set(revision.summary.clone());
+    post.category = ActiveValue::Set(revision.category);
+    post.read_time_millis = ActiveValue::Set(revision.read_time_millis);
+    post.cover_image = ActiveValue::Set(revision.cover_image.clone());
+
+    let txn = app_state.db.begin().await?;
+    let restored = post.update(&txn).await?;
+
+    let (tags, created_tags) = insert_tags(&txn, &revision_tags(&revision)).await?;
+    associate_tags_with_post(&txn, &restored, &tags).await?;
+    record_revision(&txn, &restored, &tags, actor_id, Some(revision.revision)).await?;
+    txn.commit().await?;
+
+    audit_created_tags(&app_state, &created_tags, actor_id, client_ip).await;
+
+    let after = PostResponse::new(restored.clone(), tags);
+    AuditEvent::new(AuditAction::Updated)
+        .actor(actor_id)
+        .ip(client_ip)
+        .target(AuditTarget::Post, restored.id)
+        .changes(diff(Some(&before), Some(&after)))
+        .record(&app_state.db)
+        .await;
+
+    Ok(Json(after))
+}
+
+async fn find_revision(
+    app_state: &Arc<AppState>,
+    post_id: Uuid,
+    revision: i32,
+) -> Result<PostRevision, AppError> {
+    PostRevisions::find()
+        .filter(PostRevisionColumn::PostId.eq(post_id))
+        .filter(PostRevisionColumn::Revision.eq(revision))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, REVISION_NOT_FOUND_ERROR.to_string()))
+}
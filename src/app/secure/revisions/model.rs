@@ -0,0 +1,65 @@
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use serde::{Deserialize, Serialize};
+
+use crate::app::orm::post_revisions::Model as PostRevision;
+
+#[derive(Debug, Serialize)]
+pub struct RevisionResponse {
+    pub revision: i32,
+    pub author_id: Option<Uuid>,
+    pub title: String,
+    /// The revision this one brought back, if it was created by a restore.
+    pub restored_from: Option<i32>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl RevisionResponse {
+    pub fn new(revision: PostRevision) -> Self {
+        RevisionResponse {
+            revision: revision.revision,
+            author_id: revision.author_id,
+            title: revision.title,
+            restored_from: revision.restored_from,
+            created_at: revision.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevisionDiffQuery {
+    pub from: i32,
+    pub to: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevisionDiffResponse {
+    pub from: i32,
+    pub to: i32,
+    /// Only the fields that differ between the two revisions.
+    pub fields: Vec<FieldDiff>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldDiff {
+    pub field: &'static str,
+    /// Groups of changed lines with a few unchanged lines around them.
+    pub hunks: Vec<Vec<DiffLine>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    /// 1-based line number in the `from` revision.
+    pub old_line: Option<usize>,
+    /// 1-based line number in the `to` revision.
+    pub new_line: Option<usize>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
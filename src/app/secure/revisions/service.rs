@@ -0,0 +1,230 @@
+use sea_orm::prelude::Uuid;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter,
+    QueryOrder,
+};
+use similar::{ChangeTag, TextDiff};
+
+use crate::app::common::errors::AppError;
+use crate::app::orm::post_revisions::{
+    ActiveModel as PostRevisionActiveModel, Column as PostRevisionColumn, Entity as PostRevisions,
+    Model as PostRevision,
+};
+use crate::app::orm::posts::Model as Post;
+use crate::app::orm::tags::Model as Tag;
+
+use super::model::{DiffLine, DiffOp, FieldDiff};
+
+/// Unchanged lines kept around each change in a diff.
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// Stores the post as it is now as its next revision. Call after every save.
+pub async fn record_revision(
+    db: &impl ConnectionTrait,
+    post: &Post,
+    tags: &[Tag],
+    author_id: Uuid,
+    restored_from: Option<i32>,
+) -> Result<PostRevision, AppError> {
+    let latest = PostRevisions::find()
+        .filter(PostRevisionColumn::PostId.eq(post.id))
+        .order_by_desc(PostRevisionColumn::Revision)
+        .one(db)
+        .await?;
+
+    let mut tag_names = tags.iter().map(|tag| tag.name.clone()).collect::<Vec<_>>();
+    tag_names.sort();
+
+    let revision = PostRevisionActiveModel {
+        post_id: ActiveValue::Set(post.id),
+        revision: ActiveValue::Set(latest.map_or(1, |latest| latest.revision + 1)),
+        author_id: ActiveValue::Set(Some(author_id)),
+        title: ActiveValue::Set(post.title.clone()),
+        slug: ActiveValue::Set(post.slug.clone()),
+        summary: ActiveValue::Set(post.summary.clone()),
+        content: ActiveValue::Set(post.content.clone()),
+        category: ActiveValue::Set(post.category),
+        read_time_millis: ActiveValue::Set(post.read_time_millis),
+        cover_image: ActiveValue::Set(post.cover_image.clone()),
+        tags: ActiveValue::Set(serde_json::json!(tag_names)),
+        restored_from: ActiveValue::Set(restored_from),
+        ..Default::default()
+    };
+
+    revision.insert(db).await.map_err(|err| err.into())
+}
+
+pub fn revision_tags(revision: &PostRevision) -> Vec<String> {
+    serde_json::from_value(revision.tags.clone()).unwrap_or_default()
+}
+
+/// Line by line difference of every field that changed from `from` to `to`.
+pub fn diff_revisions(from: &PostRevision, to: &PostRevision) -> Vec<FieldDiff> {
+    let fields = [
+        ("title", from.title.clone(), to.title.clone()),
+        ("slug", from.slug.clone(), to.slug.clone()),
+        ("summary", from.summary.clone(), to.summary.clone()),
+        ("content", from.content.clone(), to.content.clone()),
+        (
+            "category",
+            from.category.to_string(),
+            to.category.to_string(),
+        ),
+        (
+            "read_time_millis",
+            from.read_time_millis.to_string(),
+            to.read_time_millis.to_string(),
+        ),
+        (
+            "cover_image",
+            from.cover_image.clone(),
+            to.cover_image.clone(),
+        ),
+        (
+            "tags",
+            revision_tags(from).join("\n"),
+            revision_tags(to).join("\n"),
+        ),
+    ];
+
+    fields
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(field, old, new)| FieldDiff {
+            field,
+            hunks: diff_lines(&old, &new),
+        })
+        .collect()
+}
+
+fn diff_lines(old: &str, new: &str) -> Vec<Vec<DiffLine>> {
+    let diff = TextDiff::from_lines(old, new);
+
+    diff.grouped_ops(DIFF_CONTEXT_LINES)
+        .iter()
+        .map(|group| {
+            group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffLine {
+                    op: match change.tag() {
+                        ChangeTag::Equal => DiffOp::Equal,
+                        ChangeTag::Insert => DiffOp::Insert,
+                        ChangeTag::Delete => DiffOp::Delete,
+                    },
+                    old_line: change.old_index().map(|index| index + 1),
+                    new_line: change.new_index().map(|index| index + 1),
+                    text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn revision(number: i32, slug: &str, content: &str, tags: &[&str]) -> PostRevision {
+        PostRevision {
+            id: Uuid::new_v4(),
+            post_id: Uuid::nil(),
+            revision: number,
+            author_id: None,
+            title: "Title".to_string(),
+            slug: slug.to_string(),
+            summary: "Summary".to_string(),
+            content: content.to_string(),
+            category: Uuid::nil(),
+            read_time_millis: 1000,
+            cover_image: String::new(),
+            tags: json!(tags),
+            restored_from: None,
+            created_at: chrono::Utc::now().into(),
+        }
+    }
+
+    fn ops(lines: &[DiffLine]) -> Vec<(DiffOp, &str)> {
+        lines
+            .iter()
+            .map(|line| (line.op, line.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn identical_revisions_have_no_diff() {
+        let from = revision(1, "hello", "one\ntwo", &["rust"]);
+        let to = revision(2, "hello", "one\ntwo", &["rust"]);
+
+        assert!(diff_revisions(&from, &to).is_empty());
+    }
+
+    #[test]
+    fn lists_only_changed_fields() {
+        let from = revision(1, "hello", "same", &["rust"]);
+        let to = revision(2, "hello-world", "same", &["axum", "rust"]);
+
+        let fields = diff_revisions(&from, &to)
+            .iter()
+            .map(|diff| diff.field)
+            .collect::<Vec<_>>();
+        assert_eq!(fields, ["slug", "tags"]);
+    }
+
+    #[test]
+    fn hunks_keep_three_lines_of_context() {
+        let old = (1..=20).map(|n| n.to_string()).collect::<Vec<_>>();
+        let mut new = old.clone();
+        new[9] = "ten".to_string();
+        let from = revision(1, "hello", &old.join("\n"), &[]);
+        let to = revision(2, "hello", &new.join("\n"), &[]);
+
+        let diffs = diff_revisions(&from, &to);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].field, "content");
+        assert_eq!(diffs[0].hunks.len(), 1);
+
+        let hunk = &diffs[0].hunks[0];
+        assert_eq!(
+            ops(hunk),
+            [
+                (DiffOp::Equal, "7"),
+                (DiffOp::Equal, "8"),
+                (DiffOp::Equal, "9"),
+                (DiffOp::Delete, "10"),
+                (DiffOp::Insert, "ten"),
+                (DiffOp::Equal, "11"),
+                (DiffOp::Equal, "12"),
+                (DiffOp::Equal, "13"),
+            ]
+        );
+        assert_eq!((hunk[3].old_line, hunk[3].new_line), (Some(10), None));
+        assert_eq!((hunk[4].old_line, hunk[4].new_line), (None, Some(10)));
+    }
+
+    #[test]
+    fn distant_changes_get_separate_hunks() {
+        let old = (1..=30).map(|n| n.to_string()).collect::<Vec<_>>();
+        let mut new = old.clone();
+        new[1] = "two".to_string();
+        new[27] = "twenty-eight".to_string();
+        let from = revision(1, "hello", &old.join("\n"), &[]);
+        let to = revision(2, "hello", &new.join("\n"), &[]);
+
+        assert_eq!(diff_revisions(&from, &to)[0].hunks.len(), 2);
+    }
+
+    #[test]
+    fn reads_tags_back_from_json() {
+        assert_eq!(
+            revision_tags(&revision(1, "hello", "", &["axum", "rust"])),
+            ["axum", "rust"]
+        );
+
+        let mut broken = revision(1, "hello", "", &[]);
+        broken.tags = json!({ "not": "a list" });
+        assert!(revision_tags(&broken).is_empty());
+    }
+}
@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use sea_orm::{EntityTrait, QueryOrder, QuerySelect};
+
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::login_lockouts::{
+    Column as LoginLockoutColumn, Entity as LoginLockouts, Model as LoginLockout,
+};
+
+const RECENT_LOCKOUTS_LIMIT: u64 = 100;
+
+pub async fn get_lockouts(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<LoginLockout>>, AppError> {
+    let lockouts = LoginLockouts::find()
+        .order_by_desc(LoginLockoutColumn::CreatedAt)
+        .limit(RECENT_LOCKOUTS_LIMIT)
+        .all(&app_state.db)
+        .await?;
+
+    Ok(Json(lockouts))
+}
@@ -0,0 +1,115 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+use crate::app::auth::model::UserInfo;
+use crate::app::auth::session::service::revoke_session;
+use crate::app::common::audit::{AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::ClientIp;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::sessions::{Column as SessionColumn, Entity as Sessions};
+
+use super::model::SessionResponse;
+
+const SESSION_NOT_FOUND_ERROR: &str = "Session not found";
+
+pub async fn get_sessions(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+) -> Result<Json<Vec<SessionResponse>>, AppError> {
+    user_info.ensure_session()?;
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    let sessions = Sessions::find()
+        .filter(SessionColumn::UserId.eq(Uuid::from_str(&user_info.id).unwrap()))
+        .filter(SessionColumn::RevokedAt.is_null())
+        .filter(SessionColumn::ExpiresAt.gt(now))
+        .order_by_desc(SessionColumn::LastActiveAt)
+        .order_by_desc(SessionColumn::CreatedAt)
+        .all(&app_state.db)
+        .await?;
+
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| SessionResponse::new(session, user_info.session_id))
+            .collect(),
+    ))
+}
+
+pub async fn revoke_user_session(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    user_info.ensure_session()?;
+    let user_id = Uuid::from_str(&user_info.id).unwrap();
+
+    let session = Sessions::find_by_id(id)
+        .filter(SessionColumn::UserId.eq(user_id))
+        .filter(SessionColumn::RevokedAt.is_null())
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, SESSION_NOT_FOUND_ERROR.to_string()))?;
+
+    revoke_session(&app_state, session.id).await?;
+    record_session_revoked(&app_state, user_id, session.id, client_ip).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Signs out every other device, keeping the session making the request.
+pub async fn revoke_other_sessions(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+) -> Result<StatusCode, AppError> {
+    user_info.ensure_session()?;
+
+    let Some(current_session_id) = user_info.session_id else {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Not signed in with a session".to_string(),
+        ));
+    };
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    let user_id = Uuid::from_str(&user_info.id).unwrap();
+    let revoked = Sessions::update_many()
+        .col_expr(SessionColumn::RevokedAt, Expr::value(now))
+        .filter(SessionColumn::UserId.eq(user_id))
+        .filter(SessionColumn::Id.ne(current_session_id))
+        .filter(SessionColumn::RevokedAt.is_null())
+        .exec_with_returning(&app_state.db)
+        .await?;
+
+    for session in revoked {
+        app_state.revocations.forget_session(session.id);
+        record_session_revoked(&app_state, user_id, session.id, client_ip).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn record_session_revoked(
+    app_state: &AppState,
+    user_id: Uuid,
+    session_id: Uuid,
+    client_ip: IpAddr,
+) {
+    AuditEvent::new(AuditAction::SessionRevoked)
+        .actor(user_id)
+        .ip(client_ip)
+        .target(AuditTarget::Session, session_id)
+        .record(&app_state.db)
+        .await;
+}
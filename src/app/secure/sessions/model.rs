@@ -0,0 +1,48 @@
+use std::net::IpAddr;
+
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use serde::Serialize;
+
+use crate::app::orm::sessions::Model as Session;
+
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub id: Uuid,
+    pub created_at: DateTimeWithTimeZone,
+    pub last_active_at: Option<DateTimeWithTimeZone>,
+    pub user_agent: Option<String>,
+    /// The network the session was created from rather than the exact address.
+    pub approximate_ip: Option<String>,
+    /// Whether this is the session making the request.
+    pub current: bool,
+}
+
+impl SessionResponse {
+    pub fn new(session: Session, current_session_id: Option<Uuid>) -> Self {
+        SessionResponse {
+            id: session.id,
+            created_at: session.created_at,
+            last_active_at: session.last_active_at,
+            user_agent: session.user_agent,
+            approximate_ip: session
+                .ip_address
+                .and_then(|ip| ip.parse::<IpAddr>().ok())
+                .map(approximate_ip),
+            current: current_session_id == Some(session.id),
+        }
+    }
+}
+
+/// Masks an address down to its /24 (IPv4) or /48 (IPv6) network.
+fn approximate_ip(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            format!("{}.{}.{}.0/24", a, b, c)
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            format!("{:x}:{:x}:{:x}::/48", segments[0], segments[1], segments[2])
+        }
+    }
+}
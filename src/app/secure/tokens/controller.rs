@@ -0,0 +1,155 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+use crate::app::auth::model::{Permission, UserInfo};
+use crate::app::auth::personal_access_token::{format_scopes, generate_personal_access_token};
+use crate::app::auth::token::hash_token;
+use crate::app::common::audit::{diff, AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::ClientIp;
+use crate::app::common::core::AppState;
+use crate::app::common::errors::AppError;
+use crate::app::orm::personal_access_tokens::{
+    ActiveModel as PersonalAccessTokenActiveModel, Column as PersonalAccessTokenColumn,
+    Entity as PersonalAccessTokens,
+};
+
+use super::model::{CreateTokenRequest, CreatedTokenResponse, TokenResponse};
+
+const MAX_TOKEN_LIFETIME_DAYS: i64 = 365;
+const TOKEN_NOT_FOUND_ERROR: &str = "Token not found";
+
+pub async fn create_token(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+    Json(payload): Json<CreateTokenRequest>,
+) -> Result<Json<CreatedTokenResponse>, AppError> {
+    user_info.ensure_session()?;
+
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Token name is required".to_string(),
+        ));
+    }
+
+    if !(1..=MAX_TOKEN_LIFETIME_DAYS).contains(&payload.expires_in_days) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Token expiry must be between 1 and {} days",
+                MAX_TOKEN_LIFETIME_DAYS
+            ),
+        ));
+    }
+
+    let scopes = payload
+        .scopes
+        .iter()
+        .map(|scope| Permission::from_str(scope))
+        .collect::<Result<Vec<Permission>, String>>()
+        .map_err(|err| AppError::new(StatusCode::BAD_REQUEST, err))?;
+
+    if scopes.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "At least one scope is required".to_string(),
+        ));
+    }
+
+    if let Some(scope) = scopes
+        .iter()
+        .find(|scope| !user_info.role.has_permission(**scope))
+    {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            format!("Your role does not grant {}", scope.as_str()),
+        ));
+    }
+
+    let token = generate_personal_access_token();
+    let expires_at = chrono::Utc::now() + chrono::Duration::days(payload.expires_in_days);
+
+    let user_id = Uuid::from_str(&user_info.id).unwrap();
+    let personal_access_token = PersonalAccessTokenActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        name: ActiveValue::Set(name.to_string()),
+        token_hash: ActiveValue::Set(hash_token(&token)),
+        scopes: ActiveValue::Set(format_scopes(&scopes)),
+        expires_at: ActiveValue::Set(expires_at.into()),
+        ..Default::default()
+    }
+    .insert(&app_state.db)
+    .await?;
+    let details = TokenResponse::from(personal_access_token);
+
+    AuditEvent::new(AuditAction::TokenIssued)
+        .actor(user_id)
+        .ip(client_ip)
+        .target(AuditTarget::PersonalAccessToken, details.id)
+        .changes(diff(None, Some(&details)))
+        .record(&app_state.db)
+        .await;
+
+    Ok(Json(CreatedTokenResponse { details, token }))
+}
+
+pub async fn get_tokens(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+) -> Result<Json<Vec<TokenResponse>>, AppError> {
+    user_info.ensure_session()?;
+
+    let tokens = PersonalAccessTokens::find()
+        .filter(PersonalAccessTokenColumn::UserId.eq(Uuid::from_str(&user_info.id).unwrap()))
+        .filter(PersonalAccessTokenColumn::RevokedAt.is_null())
+        .order_by_desc(PersonalAccessTokenColumn::CreatedAt)
+        .all(&app_state.db)
+        .await?;
+
+    Ok(Json(tokens.into_iter().map(TokenResponse::from).collect()))
+}
+
+pub async fn revoke_token(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    user_info.ensure_session()?;
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    let user_id = Uuid::from_str(&user_info.id).unwrap();
+
+    let result = PersonalAccessTokens::update_many()
+        .col_expr(PersonalAccessTokenColumn::RevokedAt, Expr::value(now))
+        .filter(PersonalAccessTokenColumn::Id.eq(id))
+        .filter(PersonalAccessTokenColumn::UserId.eq(user_id))
+        .filter(PersonalAccessTokenColumn::RevokedAt.is_null())
+        .exec(&app_state.db)
+        .await?;
+
+    if result.rows_affected == 0 {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            TOKEN_NOT_FOUND_ERROR.to_string(),
+        ));
+    }
+
+    AuditEvent::new(AuditAction::TokenRevoked)
+        .actor(user_id)
+        .ip(client_ip)
+        .target(AuditTarget::PersonalAccessToken, id)
+        .record(&app_state.db)
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,46 @@
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use serde::{Deserialize, Serialize};
+
+use crate::app::auth::personal_access_token::parse_scopes;
+use crate::app::orm::personal_access_tokens::Model as PersonalAccessToken;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_in_days: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<&'static str>,
+    pub created_at: DateTimeWithTimeZone,
+    pub expires_at: DateTimeWithTimeZone,
+    pub last_used_at: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedTokenResponse {
+    #[serde(flatten)]
+    pub details: TokenResponse,
+    /// Only returned once, the server keeps nothing but its hash.
+    pub token: String,
+}
+
+impl From<PersonalAccessToken> for TokenResponse {
+    fn from(token: PersonalAccessToken) -> Self {
+        TokenResponse {
+            id: token.id,
+            name: token.name,
+            scopes: parse_scopes(&token.scopes)
+                .iter()
+                .map(|scope| scope.as_str())
+                .collect(),
+            created_at: token.created_at,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+        }
+    }
+}
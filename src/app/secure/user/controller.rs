@@ -1,17 +1,28 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
+use axum::response::{IntoResponse, Response};
 use axum::{extract::State, http::StatusCode, Extension, Json};
-use sea_orm::{EntityTrait, QuerySelect};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
 use uuid::Uuid;
 
+use crate::app::auth::login::controller::record_failed_login;
+use crate::app::auth::password::hash_password;
+use crate::app::auth::session::service::{
+    create_session, revoke_user_sessions, session_cookie_headers,
+};
+use crate::app::common::audit::{redacted_change, AuditAction, AuditEvent, AuditTarget};
+use crate::app::common::client_ip::ClientInfo;
+use crate::app::common::rate_limit::too_many_requests;
+use crate::app::orm::sessions::Entity as Sessions;
 use crate::app::orm::users::{Column, Entity as Users, Model as User};
 use crate::app::{
     auth::model::UserInfo,
     common::{core::AppState, errors::AppError},
 };
 
-use super::model::CurrentUserResponse;
+use super::model::{ChangePasswordRequest, CurrentUserResponse};
 
 impl From<User> for CurrentUserResponse {
     fn from(user: User) -> Self {
@@ -20,6 +31,8 @@ impl From<User> for CurrentUserResponse {
             name: user.name,
             email: user.email,
             avatar: user.avatar,
+            role: user.role,
+            passwordless_enabled: user.passwordless_enabled,
         }
     }
 }
@@ -31,7 +44,14 @@ pub async fn get_current_user_info(
     let user_id = user_info.id;
 
     let user = Users::find_by_id(Uuid::from_str(&user_id).unwrap())
-        .columns([Column::Id, Column::Name, Column::Email, Column::Avatar])
+        .columns([
+            Column::Id,
+            Column::Name,
+            Column::Email,
+            Column::Avatar,
+            Column::Role,
+            Column::PasswordlessEnabled,
+        ])
         .one(&app_state.db)
         .await?;
 
@@ -47,3 +67,79 @@ pub async fn get_current_user_info(
 
     Ok(Json(user.into()))
 }
+
+/// Changes the password after checking the current one. Every other session
+/// and its access tokens are signed out; the caller gets a fresh session in
+/// return. Personal access tokens are left alone.
+pub async fn change_password(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user_info): Extension<UserInfo>,
+    client: ClientInfo,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<Response, AppError> {
+    user_info.ensure_session()?;
+
+    let user = Users::find_by_id(Uuid::from_str(&user_info.id).unwrap())
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "User not found".to_string()))?;
+
+    // a stolen session must not be usable to guess the password
+    let throttle_key = user.email.to_lowercase();
+    let client_ip = client.ip;
+    if let Err(retry_after) = app_state.login_throttle.check(&throttle_key, client_ip) {
+        return Ok(too_many_requests(retry_after));
+    }
+
+    if !bcrypt::verify(&payload.current_password, &user.password_hash).unwrap_or(false) {
+        record_failed_login(&app_state, &throttle_key, client_ip).await?;
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "Current password is incorrect".to_string(),
+        ));
+    }
+
+    if payload.new_password == payload.current_password {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "The new password must be different from the current one".to_string(),
+        ));
+    }
+
+    app_state.password_policy.validate(&payload.new_password)?;
+    let password_hash = hash_password(&payload.new_password, app_state.config.bcrypt_cost)?;
+
+    Users::update_many()
+        .col_expr(Column::PasswordHash, Expr::value(password_hash))
+        .filter(Column::Id.eq(user.id))
+        .exec(&app_state.db)
+        .await?;
+
+    AuditEvent::new(AuditAction::Updated)
+        .actor(user.id)
+        .ip(client_ip)
+        .target(AuditTarget::User, user.id)
+        .changes(redacted_change("password_hash"))
+        .record(&app_state.db)
+        .await;
+
+    // the replacement session keeps the "remember me" choice of the current one
+    let remember_me = match user_info.session_id {
+        Some(session_id) => Sessions::find_by_id(session_id)
+            .one(&app_state.db)
+            .await?
+            .is_some_and(|session| session.remember_me),
+        None => false,
+    };
+
+    app_state
+        .revocations
+        .revoke_all_for_user(&app_state.db, user.id)
+        .await?;
+    revoke_user_sessions(&app_state, user.id).await?;
+
+    let issued = create_session(&app_state, user.id, &client, remember_me).await?;
+    let headers = session_cookie_headers(&app_state, &user, issued)?;
+
+    Ok((headers, StatusCode::NO_CONTENT).into_response())
+}
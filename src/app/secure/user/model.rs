@@ -1,4 +1,4 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 #[derive(Debug, Serialize)]
@@ -7,4 +7,12 @@ pub struct CurrentUserResponse {
     pub name: String,
     pub email: String,
     pub avatar: Option<String>,
+    pub role: String,
+    pub passwordless_enabled: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
 }
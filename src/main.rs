@@ -3,6 +3,7 @@ mod app;
 use s3::{creds::Credentials, Region};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use dotenvy::dotenv;
 use sea_orm::{Database, DatabaseConnection};
@@ -10,10 +11,26 @@ use tracing::Level;
 use tracing_subscriber::fmt::Subscriber;
 
 use crate::app::{
-    common::core::{AppState, Config},
+    auth::{
+        keys::KeyStore,
+        login::throttle::LoginThrottle,
+        oidc::service::OidcClient,
+        password::PasswordPolicy,
+        revocation::RevocationCache,
+        session::activity::{flush_session_activity, SessionActivity},
+    },
+    common::{
+        core::{AppState, Config},
+        mailer::mailer_from_config,
+        rate_limit::RateLimiter,
+    },
     router::create_router,
+    secure::posts::scheduler::run_post_scheduler,
 };
 
+const ACCOUNT_EMAILS_PER_HOUR: usize = 5;
+const PASSKEY_CHALLENGES_PER_MINUTE: usize = 20;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let subscriber = Subscriber::builder().with_max_level(Level::DEBUG).finish();
@@ -31,24 +48,59 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Some("b2_creds"),
     )?;
 
-    let region = Region::Custom { region: config.b2_region.to_string(), endpoint: config.b2_endpoint.to_string() };
+    let region = Region::Custom {
+        region: config.b2_region.to_string(),
+        endpoint: config.b2_endpoint.to_string(),
+    };
 
-    let public_bucket = s3::bucket::Bucket::new(
-        &config.b2_public_bucket,
-        region,
-        creds,
-    )?;
+    let public_bucket = s3::bucket::Bucket::new(&config.b2_public_bucket, region, creds)?;
+
+    let keys = KeyStore::load(&config.jwt_keys_dir, &config.jwt_signing_key_id)?;
+
+    let upload_quota =
+        RateLimiter::new(config.media_uploads_per_hour, Duration::from_secs(60 * 60));
+
+    let mailer = mailer_from_config(&config)?;
+    let email_quota = RateLimiter::new(ACCOUNT_EMAILS_PER_HOUR, Duration::from_secs(60 * 60));
+    let passkey_challenge_quota =
+        RateLimiter::new(PASSKEY_CHALLENGES_PER_MINUTE, Duration::from_secs(60));
+
+    let password_policy = PasswordPolicy::load(&config)?;
+
+    let oidc = match config.oidc {
+        Some(_) => Some(OidcClient::new()?),
+        None => None,
+    };
 
     let addr: SocketAddr = format!("{}:{}", config.host, config.port).parse()?;
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    let router = create_router(Arc::new(AppState {
+    let app_state = Arc::new(AppState {
         db,
         config,
         public_bucket,
-    }));
+        keys,
+        revocations: RevocationCache::default(),
+        upload_quota,
+        login_throttle: LoginThrottle::default(),
+        mailer,
+        email_quota,
+        passkey_challenge_quota,
+        oidc,
+        password_policy,
+        session_activity: SessionActivity::default(),
+    });
+
+    tokio::spawn(flush_session_activity(app_state.clone()));
+    tokio::spawn(run_post_scheduler(app_state.clone()));
+
+    let router = create_router(app_state);
 
     println!("Running on http://{}", addr);
-    axum::serve(listener, router).await?;
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }